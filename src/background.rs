@@ -0,0 +1,24 @@
+pub mod gradient;
+pub mod constant;
+pub use self::gradient::Gradient;
+
+use crate::vector::{Vec3, Color};
+
+// What rays that escape the scene see
+pub trait Background: Send + Sync {
+    // Radiance arriving from the given direction
+    fn color(&self, direction: Vec3<f64>) -> Color;
+
+    // Picks a direction to light the scene from, returning it with the
+    // radiance arriving from it and its solid angle pdf. Backgrounds
+    // without importance sampling only light the scene through rays
+    // that happen to escape.
+    fn sample(&self) -> Option<(Vec3<f64>, Color, f64)> {
+        None
+    }
+
+    // Solid angle pdf of sample returning the given direction
+    fn pdf(&self, _direction: Vec3<f64>) -> f64 {
+        0.0
+    }
+}
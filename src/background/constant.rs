@@ -0,0 +1,9 @@
+use crate::background::Background;
+use crate::vector::{Vec3, Color};
+
+// Plain colors act as a uniform background
+impl Background for Color {
+    fn color(&self, _direction: Vec3<f64>) -> Color {
+        *self
+    }
+}
@@ -0,0 +1,30 @@
+use crate::background::Background;
+use crate::vector::{Vec3, Color};
+
+// Vertical blend from straight down to straight up, the sky of Ray
+// Tracing in One Weekend goes from white to (0.5, 0.7, 1.0)
+#[derive(Clone)]
+pub struct Gradient {
+    pub bottom: Color,
+    pub top: Color,
+}
+
+impl Background for Gradient {
+    fn color(&self, direction: Vec3<f64>) -> Color {
+        let t = 0.5 * (direction.normalized().y + 1.0);
+        self.bottom * (1.0 - t) + self.top * t
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color() {
+        let gradient = Gradient { bottom: Color::new(1.0, 0.5, 0.0), top: Color::new(0.0, 0.5, 1.0) };
+        assert_eq!(gradient.color(Vec3::new(0.0, 2.0, 0.0)), gradient.top);
+        assert_eq!(gradient.color(Vec3::new(0.0, -2.0, 0.0)), gradient.bottom);
+        assert_eq!(gradient.color(Vec3::new(1.0, 0.0, 0.0)), Color::new(0.5, 0.5, 0.5));
+    }
+}
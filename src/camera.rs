@@ -1,55 +1,31 @@
-use crate::vector::Vec3;
-use crate::ray::Ray;
-
-pub struct Camera {
-    pub origin: Vec3<f64>,
-    pub horizontal: Vec3<f64>,
-    pub vertical: Vec3<f64>,
-    pub lower_left_corner: Vec3<f64>,
-    pub u: Vec3<f64>,
-    pub v: Vec3<f64>,
-    pub w: Vec3<f64>,
-    pub lens_radius: f64,
-    pub aspect_ratio: f64,
-}
+pub mod perspective;
+pub mod orthographic;
+pub mod equirectangular;
+pub mod fisheye;
+pub mod stereo;
+pub mod aperture;
+pub use self::perspective::Perspective;
+pub use self::orthographic::Orthographic;
+pub use self::equirectangular::Equirectangular;
+pub use self::fisheye::Fisheye;
+pub use self::stereo::{Stereo, StereoLayout};
+pub use self::aperture::{Aperture, AperturePolygon, ApertureMask};
 
-impl Camera {
-    pub fn new(lookfrom: Vec3<f64>, lookat: Vec3<f64>, vup: Vec3<f64>,
-        vfov: f64, aspect_ratio: f64, aperture: f64, focus_dist: f64) -> Self {
-        let theta = vfov.to_radians();
-        let h = (theta/2.0).tan();
-        let viewport_height = 2.0 * h;
-        let viewport_width = aspect_ratio * viewport_height;
-
-        let w = (lookfrom - lookat).normalized();
-        let u = vup.cross(w).normalized();
-        let v = w.cross(u);
+use crate::ray::Ray;
 
-        let origin = lookfrom;
-        let horizontal = u * viewport_width * focus_dist;
-        let vertical = v * viewport_height * focus_dist;
-        let lower_left_corner = origin - horizontal/2.0 - vertical/2.0 - w*focus_dist;
+// Generates primary rays from film coordinates, s going left to right
+// and t bottom to top, both over [0, 1]. There is no ray for parts of
+// the film the lens doesn't cover, like outside a fisheye's image circle.
+pub trait Camera: Send + Sync {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray>;
 
-        Self {
-            origin,
-            horizontal,
-            vertical,
-            lower_left_corner,
-            u,
-            v,
-            w,
-            lens_radius: aperture / 2.0,
-            aspect_ratio
-        }
-    }
+    // Width over height of the film
+    fn aspect_ratio(&self) -> f64;
 
-    pub fn get_ray(&self, s: f64, t: f64) -> Ray {
-        let rd = Vec3::random_in_unit_disk() * self.lens_radius;
-        let offset = self.u * rd.x + self.v * rd.y;
-        Ray {
-            origin: self.origin + offset,
-            direction: self.lower_left_corner + self.horizontal*s
-                + self.vertical*t - self.origin - offset,
-        }
+    // Ray along with its differentials towards (s + ds, t) and (s, t + dt),
+    // where ds and dt are the size of a pixel in film coordinates. Cameras
+    // that don't track differentials leave textures point sampled.
+    fn get_ray_differential(&self, s: f64, t: f64, _ds: f64, _dt: f64) -> Option<Ray> {
+        self.get_ray(s, t)
     }
 }
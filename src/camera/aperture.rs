@@ -0,0 +1,132 @@
+use crate::environment::{Distribution2D, texel_luminance};
+use crate::texture::ImageTexture;
+use crate::vector::Vec3;
+use rand::{thread_rng, Rng};
+use std::f64::consts::PI;
+
+// Shape of the lens opening, which out of focus highlights (bokeh) take
+// on. Shapes are sized to the unit circle, scaled by the lens radius.
+#[derive(Clone)]
+pub enum Aperture {
+    Circle,
+    Polygon(AperturePolygon),
+    Mask(ApertureMask),
+}
+
+impl Aperture {
+    pub fn polygon(blades: u32, rotation: f64) -> Self {
+        Aperture::Polygon(AperturePolygon::new(blades, rotation))
+    }
+
+    // Point on the lens, x along the camera's u and y along v
+    pub fn sample(&self) -> (f64, f64) {
+        let mut rng = thread_rng();
+        match self {
+            Aperture::Circle => {
+                let p = Vec3::random_in_unit_disk();
+                (p.x, p.y)
+            }
+            Aperture::Polygon(polygon) => {
+                // All the triangles fanning out from the middle have the
+                // same area, pick one then a point in it
+                let step = 2.0 * PI / polygon.blades as f64;
+                let k = rng.gen_range(0..polygon.blades) as f64;
+                let a0 = PI / 2.0 + polygon.rotation.to_radians() + step * k;
+                let a1 = a0 + step;
+                let (mut b0, mut b1): (f64, f64) = (rng.gen(), rng.gen());
+                if b0 + b1 > 1.0 {
+                    (b0, b1) = (1.0 - b0, 1.0 - b1);
+                }
+                (b0 * a0.cos() + b1 * a1.cos(), b0 * a0.sin() + b1 * a1.sin())
+            }
+            Aperture::Mask(mask) => {
+                let (u, v, _) = mask.distribution.sample(rng.gen(), rng.gen());
+                (2.0*u - 1.0, 2.0*v - 1.0)
+            }
+        }
+    }
+}
+
+// Regular polygon inscribed in the circle, as formed by straight
+// diaphragm blades. The rotation is in degrees counter-clockwise, with
+// the first corner straight up at 0.
+#[derive(Copy, Clone, Debug)]
+pub struct AperturePolygon {
+    blades: u32,
+    rotation: f64,
+}
+
+impl AperturePolygon {
+    pub fn new(blades: u32, rotation: f64) -> Self {
+        assert!(blades >= 3, "polygon aperture needs at least 3 blades, got {blades}");
+        Self { blades, rotation }
+    }
+
+    pub fn blades(&self) -> u32 {
+        self.blades
+    }
+
+    pub fn rotation(&self) -> f64 {
+        self.rotation
+    }
+}
+
+// Image of the aperture covering the square around the unit circle, how
+// bright each texel is sets how much light passes through there
+#[derive(Clone)]
+pub struct ApertureMask {
+    distribution: Distribution2D,
+}
+
+impl ApertureMask {
+    pub fn new(texture: &ImageTexture) -> Self {
+        Self { distribution: Distribution2D::new(texel_luminance(texture)) }
+    }
+
+    pub fn load(filepath: String) -> Self {
+        ApertureMask::new(&ImageTexture::load(filepath))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polygon() {
+        // Square with its corners on the axes
+        let aperture = Aperture::polygon(4, 0.0);
+        for _ in 0..1000 {
+            let (x, y) = aperture.sample();
+            assert!(x.abs() + y.abs() <= 1.0 + 1e-12);
+        }
+
+        // Turned by 45 degrees it's axis aligned, and the samples cover it
+        let aperture = Aperture::polygon(4, 45.0);
+        let half = std::f64::consts::FRAC_1_SQRT_2;
+        let mut quadrants = [0; 4];
+        for _ in 0..1000 {
+            let (x, y) = aperture.sample();
+            assert!(x.abs() <= half + 1e-12 && y.abs() <= half + 1e-12);
+            quadrants[(x > 0.0) as usize + 2 * (y > 0.0) as usize] += 1;
+        }
+        assert!(quadrants.iter().all(|&n| n > 150));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_polygon_too_few_blades() {
+        Aperture::polygon(2, 0.0);
+    }
+
+    #[test]
+    fn test_mask() {
+        // Only the top right texel of a 2x2 mask lets light through
+        let texels = vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+        let aperture = Aperture::Mask(ApertureMask::new(&ImageTexture::new(texels, vec![], 2, 2)));
+        for _ in 0..100 {
+            let (x, y) = aperture.sample();
+            assert!((0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y));
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use crate::camera::Camera;
+use crate::vector::Vec3;
+use crate::ray::{Ray, RayDifferential};
+use crate::environment::direction_from_uv;
+
+// Full 360x180 degree latitude-longitude panorama from a single point,
+// laid out the way 360 image viewers and VR players expect and the way
+// Environment reads maps. The middle of the image looks towards lookat,
+// with the top row straight up, so rendering with lookat along +x gives
+// a map that loads back as an environment in the orientation it was
+// rendered.
+pub struct Equirectangular {
+    pub origin: Vec3<f64>,
+    pub u: Vec3<f64>,
+    pub v: Vec3<f64>,
+    pub w: Vec3<f64>,
+    // Omni-directional stereo, rays start this far to the right of origin
+    // (negative for the left eye) relative to the horizontal direction
+    // they look in, turned in to meet at the convergence distance
+    pub eye_offset: f64,
+    pub convergence: f64,
+}
+
+impl Equirectangular {
+    pub fn new(lookfrom: Vec3<f64>, lookat: Vec3<f64>, vup: Vec3<f64>) -> Self {
+        let w = (lookfrom - lookat).normalized();
+        let u = vup.cross(w).normalized();
+        let v = w.cross(u);
+
+        Self { origin: lookfrom, u, v, w, eye_offset: 0.0, convergence: f64::INFINITY }
+    }
+
+    // One eye of an omni-directional stereo pair, every column of the
+    // panorama is seen from a different point on a circle of radius
+    // offset. An infinite convergence keeps each eye's rays parallel to
+    // the mono panorama's.
+    pub fn eye(&self, offset: f64, convergence: f64) -> Self {
+        Self { eye_offset: offset, convergence, ..*self }
+    }
+
+    // Maps have the middle at +x and up at +y, with u running towards +z,
+    // to the right when looking along +x
+    fn map_direction(&self, s: f64, t: f64) -> Vec3<f64> {
+        let d = direction_from_uv(s, t);
+        -self.w * d.x + self.v * d.y + self.u * d.z
+    }
+
+    fn eye_position(&self, s: f64) -> Vec3<f64> {
+        let right = self.map_direction(s, 0.5).cross(self.v);
+        right * self.eye_offset
+    }
+
+    fn origin(&self, s: f64) -> Vec3<f64> {
+        self.origin + self.eye_position(s)
+    }
+
+    fn direction(&self, s: f64, t: f64) -> Vec3<f64> {
+        let d = self.map_direction(s, t);
+        if self.eye_offset == 0.0 || self.convergence.is_infinite() {
+            return d;
+        }
+        d * self.convergence - self.eye_position(s)
+    }
+}
+
+impl Camera for Equirectangular {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        Some(Ray::new(self.origin(s), self.direction(s, t)))
+    }
+
+    fn aspect_ratio(&self) -> f64 {
+        2.0
+    }
+
+    fn get_ray_differential(&self, s: f64, t: f64, ds: f64, dt: f64) -> Option<Ray> {
+        Some(Ray {
+            origin: self.origin(s),
+            direction: self.direction(s, t),
+            wavelength: None,
+            differential: Some(RayDifferential {
+                rx_origin: self.origin(s + ds),
+                rx_direction: self.direction(s + ds, t),
+                ry_origin: self.origin(s),
+                ry_direction: self.direction(s, t + dt),
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::uv_from_direction;
+
+    #[test]
+    fn test_environment_layout() {
+        let up = Vec3::new(0.0, 1.0, 0.0);
+
+        // Looking along +x the panorama is laid out exactly like a map
+        let cam = Equirectangular::new(Vec3::zero(), Vec3::new(1.0, 0.0, 0.0), up);
+        for (s, t) in [(0.1, 0.3), (0.5, 0.5), (0.8, 0.9)] {
+            let r = cam.get_ray(s, t).unwrap();
+            let (u, v) = uv_from_direction(r.direction.normalized());
+            assert!((u - s).abs() < 1e-9 && (v - t).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_layout() {
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let cam = Equirectangular::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0), up);
+
+        // The middle looks at lookat, a quarter turn to the right is three
+        // quarters across and the top and bottom rows are the poles
+        let at = |s: f64, t: f64| cam.get_ray(s, t).unwrap().direction;
+        assert!((at(0.5, 0.5) - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-9);
+        assert!((at(0.75, 0.5) - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-9);
+        assert!((at(0.0, 0.5) - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+        assert!((at(0.3, 1.0) - up).length() < 1e-9);
+        assert!((at(0.3, 0.0) + up).length() < 1e-9);
+
+        // Latitude is linear in t
+        let d = at(0.5, 0.75);
+        assert!((d.y - std::f64::consts::FRAC_1_SQRT_2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_omnidirectional_stereo() {
+        let cam = Equirectangular::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0));
+        let left = cam.eye(-0.03, f64::INFINITY);
+        let right = cam.eye(0.03, 2.0);
+
+        // Eyes sit to either side of the direction looked in, whichever
+        // way that is
+        let l = left.get_ray(0.5, 0.5).unwrap();
+        assert!((l.origin - Vec3::new(-0.03, 0.0, 0.0)).length() < 1e-12);
+        assert!((l.direction - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-12);
+        let l = left.get_ray(0.25, 0.7).unwrap();
+        assert!(l.origin.dot(l.direction).abs() < 1e-12);
+        assert!((l.origin.length() - 0.03).abs() < 1e-12);
+
+        // Converged rays pass through the point the mono ray reaches at
+        // the convergence distance
+        for (s, t) in [(0.5, 0.5), (0.1, 0.3)] {
+            let r = right.get_ray(s, t).unwrap();
+            let mono = cam.get_ray(s, t).unwrap();
+            let target = mono.direction * 2.0;
+            assert!((r.origin + r.direction - target).length() < 1e-12);
+        }
+    }
+}
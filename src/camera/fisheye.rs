@@ -0,0 +1,107 @@
+use crate::camera::Camera;
+use crate::vector::Vec3;
+use crate::ray::{Ray, RayDifferential};
+
+// Equidistant (angular) fisheye, the angle away from the view direction
+// grows linearly with distance from the middle of the film. The image
+// circle spans fov degrees and fits the film's height, the film outside
+// of it stays black. Fields of view up to 360 degrees are supported.
+pub struct Fisheye {
+    pub origin: Vec3<f64>,
+    pub u: Vec3<f64>,
+    pub v: Vec3<f64>,
+    pub w: Vec3<f64>,
+    pub fov: f64,
+    pub aspect_ratio: f64,
+}
+
+impl Fisheye {
+    pub fn new(lookfrom: Vec3<f64>, lookat: Vec3<f64>, vup: Vec3<f64>,
+        fov: f64, aspect_ratio: f64) -> Self {
+        let w = (lookfrom - lookat).normalized();
+        let u = vup.cross(w).normalized();
+        let v = w.cross(u);
+
+        Self { origin: lookfrom, u, v, w, fov, aspect_ratio }
+    }
+
+    // Film position relative to the image circle, which has radius 1
+    fn film(&self, s: f64, t: f64) -> (f64, f64) {
+        ((2.0*s - 1.0) * self.aspect_ratio, 2.0*t - 1.0)
+    }
+
+    fn direction(&self, x: f64, y: f64) -> Vec3<f64> {
+        let r = (x*x + y*y).sqrt();
+        let theta = r * self.fov.to_radians() / 2.0;
+        if r == 0.0 {
+            return -self.w;
+        }
+        (self.u * x + self.v * y) * (theta.sin() / r) - self.w * theta.cos()
+    }
+}
+
+impl Camera for Fisheye {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        let (x, y) = self.film(s, t);
+        if x*x + y*y > 1.0 {
+            return None;
+        }
+        Some(Ray::new(self.origin, self.direction(x, y)))
+    }
+
+    fn aspect_ratio(&self) -> f64 {
+        self.aspect_ratio
+    }
+
+    fn get_ray_differential(&self, s: f64, t: f64, ds: f64, dt: f64) -> Option<Ray> {
+        let (x, y) = self.film(s, t);
+        if x*x + y*y > 1.0 {
+            return None;
+        }
+        // Offsets may fall just outside the circle, the mapping carries on
+        // smoothly there
+        let (dx, dy) = (2.0 * ds * self.aspect_ratio, 2.0 * dt);
+        Some(Ray {
+            origin: self.origin,
+            direction: self.direction(x, y),
+            wavelength: None,
+            differential: Some(RayDifferential {
+                rx_origin: self.origin,
+                rx_direction: self.direction(x + dx, y),
+                ry_origin: self.origin,
+                ry_direction: self.direction(x, y + dy),
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_circle() {
+        let cam = Fisheye::new(
+            Vec3::zero(),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            180.0,
+            2.0
+        );
+
+        let center = cam.get_ray(0.5, 0.5).unwrap();
+        assert!((center.direction - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-12);
+
+        // The edge of the circle is fov/2 off axis, halfway there is half
+        // the angle
+        let top = cam.get_ray(0.5, 1.0).unwrap();
+        assert!((top.direction - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-12);
+        let right = cam.get_ray(0.625, 0.5).unwrap();
+        let half = std::f64::consts::FRAC_1_SQRT_2;
+        assert!((right.direction - Vec3::new(half, 0.0, -half)).length() < 1e-12);
+
+        // The corners of a wide film are outside the circle
+        assert!(cam.get_ray(0.0, 0.0).is_none());
+        assert!(cam.get_ray(0.9, 0.5).is_none());
+    }
+}
@@ -0,0 +1,92 @@
+use crate::camera::Camera;
+use crate::vector::Vec3;
+use crate::ray::{Ray, RayDifferential};
+
+// Parallel projection, all rays point along the view direction and start
+// on a view_width wide rectangle through lookfrom. Sizes on the film
+// don't change with distance.
+pub struct Orthographic {
+    pub lower_left_corner: Vec3<f64>,
+    pub horizontal: Vec3<f64>,
+    pub vertical: Vec3<f64>,
+    pub direction: Vec3<f64>,
+    pub aspect_ratio: f64,
+}
+
+impl Orthographic {
+    pub fn new(lookfrom: Vec3<f64>, lookat: Vec3<f64>, vup: Vec3<f64>,
+        view_width: f64, aspect_ratio: f64) -> Self {
+        let w = (lookfrom - lookat).normalized();
+        let u = vup.cross(w).normalized();
+        let v = w.cross(u);
+
+        let horizontal = u * view_width;
+        let vertical = v * (view_width / aspect_ratio);
+
+        Self {
+            lower_left_corner: lookfrom - horizontal/2.0 - vertical/2.0,
+            horizontal,
+            vertical,
+            direction: -w,
+            aspect_ratio
+        }
+    }
+
+    fn origin(&self, s: f64, t: f64) -> Vec3<f64> {
+        self.lower_left_corner + self.horizontal*s + self.vertical*t
+    }
+}
+
+impl Camera for Orthographic {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        Some(Ray::new(self.origin(s, t), self.direction))
+    }
+
+    fn aspect_ratio(&self) -> f64 {
+        self.aspect_ratio
+    }
+
+    fn get_ray_differential(&self, s: f64, t: f64, ds: f64, dt: f64) -> Option<Ray> {
+        Some(Ray {
+            origin: self.origin(s, t),
+            direction: self.direction,
+            wavelength: None,
+            differential: Some(RayDifferential {
+                rx_origin: self.origin(s + ds, t),
+                rx_direction: self.direction,
+                ry_origin: self.origin(s, t + dt),
+                ry_direction: self.direction,
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parallel_rays() {
+        let cam = Orthographic::new(
+            Vec3::new(0.0, 0.0, 5.0),
+            Vec3::zero(),
+            Vec3::new(0.0, 1.0, 0.0),
+            4.0,
+            2.0
+        );
+
+        let center = cam.get_ray(0.5, 0.5).unwrap();
+        assert!((center.origin - Vec3::new(0.0, 0.0, 5.0)).length() < 1e-12);
+        assert!((center.direction - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-12);
+
+        // The view is 4 wide and 2 high
+        let corner = cam.get_ray(1.0, 1.0).unwrap();
+        assert!((corner.origin - Vec3::new(2.0, 1.0, 5.0)).length() < 1e-12);
+        assert!((corner.direction - center.direction).length() < 1e-12);
+
+        let r = cam.get_ray_differential(0.0, 0.0, 0.25, 0.5).unwrap();
+        let rd = r.differential.unwrap();
+        assert!((rd.rx_origin - r.origin - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-12);
+        assert!((rd.ry_origin - r.origin - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-12);
+    }
+}
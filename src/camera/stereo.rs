@@ -0,0 +1,98 @@
+use crate::camera::Camera;
+use crate::ray::Ray;
+
+// Where each eye goes on the film
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StereoLayout {
+    // Left eye on the left half
+    SideBySide,
+    // Left eye on the top half
+    TopBottom,
+}
+
+// Renders two eyes into one image for VR, the eyes are usually made with
+// Perspective::eye or Equirectangular::eye from a mono camera
+pub struct Stereo {
+    pub left: Box<dyn Camera>,
+    pub right: Box<dyn Camera>,
+    pub layout: StereoLayout,
+}
+
+impl Stereo {
+    pub fn new(left: Box<dyn Camera>, right: Box<dyn Camera>, layout: StereoLayout) -> Self {
+        Self { left, right, layout }
+    }
+
+    // The eye that sees a point on the film, with the point in its own
+    // film coordinates
+    fn eye(&self, s: f64, t: f64) -> (&dyn Camera, f64, f64) {
+        match self.layout {
+            StereoLayout::SideBySide if s < 0.5 => (self.left.as_ref(), 2.0 * s, t),
+            StereoLayout::SideBySide => (self.right.as_ref(), 2.0 * s - 1.0, t),
+            StereoLayout::TopBottom if t >= 0.5 => (self.left.as_ref(), s, 2.0 * t - 1.0),
+            StereoLayout::TopBottom => (self.right.as_ref(), s, 2.0 * t),
+        }
+    }
+}
+
+impl Camera for Stereo {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        let (eye, s, t) = self.eye(s, t);
+        eye.get_ray(s, t)
+    }
+
+    fn aspect_ratio(&self) -> f64 {
+        match self.layout {
+            StereoLayout::SideBySide => 2.0 * self.left.aspect_ratio(),
+            StereoLayout::TopBottom => self.left.aspect_ratio() / 2.0,
+        }
+    }
+
+    fn get_ray_differential(&self, s: f64, t: f64, ds: f64, dt: f64) -> Option<Ray> {
+        let (eye, s, t) = self.eye(s, t);
+        match self.layout {
+            StereoLayout::SideBySide => eye.get_ray_differential(s, t, 2.0 * ds, dt),
+            StereoLayout::TopBottom => eye.get_ray_differential(s, t, ds, 2.0 * dt),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Orthographic;
+    use crate::vector::Vec3;
+
+    #[test]
+    fn test_layout() {
+        let cam = Orthographic::new(
+            Vec3::new(0.0, 0.0, 5.0),
+            Vec3::zero(),
+            Vec3::new(0.0, 1.0, 0.0),
+            2.0,
+            1.0
+        );
+        let eye = |offset: f64| Box::new(Orthographic {
+            lower_left_corner: cam.lower_left_corner + Vec3::new(offset, 0.0, 0.0),
+            ..cam
+        });
+
+        let stereo = Stereo::new(eye(-1.0), eye(1.0), StereoLayout::SideBySide);
+        assert_eq!(stereo.aspect_ratio(), 2.0);
+        let left = stereo.get_ray(0.25, 0.5).unwrap();
+        assert!((left.origin - Vec3::new(-1.0, 0.0, 5.0)).length() < 1e-12);
+        let right = stereo.get_ray_differential(0.75, 0.5, 0.25, 0.25).unwrap();
+        assert!((right.origin - Vec3::new(1.0, 0.0, 5.0)).length() < 1e-12);
+        // A pixel covers twice as much of each eye's view
+        let rd = right.differential.unwrap();
+        assert!((rd.rx_origin - right.origin - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-12);
+        assert!((rd.ry_origin - right.origin - Vec3::new(0.0, 0.5, 0.0)).length() < 1e-12);
+
+        let stereo = Stereo::new(eye(-1.0), eye(1.0), StereoLayout::TopBottom);
+        assert_eq!(stereo.aspect_ratio(), 0.5);
+        let left = stereo.get_ray(0.5, 0.75).unwrap();
+        assert!((left.origin - Vec3::new(-1.0, 0.0, 5.0)).length() < 1e-12);
+        let right = stereo.get_ray(0.5, 0.25).unwrap();
+        assert!((right.origin - Vec3::new(1.0, 0.0, 5.0)).length() < 1e-12);
+    }
+}
@@ -0,0 +1,298 @@
+use crate::vector::{Vec3, Color};
+use crate::background::Background;
+use crate::spectrum;
+use crate::texture::{Texture, TexCoord, ImageTexture, Filter, Wrap};
+use rand::{thread_rng, Rng};
+use std::f64::consts::PI;
+
+// Latitude-longitude map of the light arriving from every direction,
+// typically a high dynamic range .hdr or .exr capture
+#[derive(Clone)]
+pub struct Environment {
+    // Only set by new, which builds the distribution from it
+    texture: ImageTexture,
+    // Multiplier on the map's radiance
+    pub intensity: f64,
+    // Rotation of the map about the vertical axis, in degrees
+    pub rotation: f64,
+    // Brightness of the texture used to sample bright directions,
+    // built from the texture by new
+    distribution: Distribution2D,
+}
+
+impl Environment {
+    pub fn new(mut texture: ImageTexture) -> Self {
+        // Smooth and seamless across the left and right edges, the top and
+        // bottom rows are the poles and don't continue into each other
+        texture.filter = Filter::Bilinear;
+        texture.wrap_u = Wrap::Repeat;
+        texture.wrap_v = Wrap::Clamp;
+
+        // Luminance per texel, weighted by sin(theta) since rows near the
+        // poles cover less solid angle
+        let (width, height) = (texture.width() as usize, texture.height() as usize);
+        let luminance = texel_luminance(&texture);
+
+        // Bilinear lookups spread each texel into its neighbours, so take
+        // the brightest of them or dim texels next to a small sun would be
+        // picked far too rarely for the light they return
+        let rows = (0..height).map(|j| {
+            let sin_theta = (PI * (j as f64 + 0.5) / height as f64).sin();
+            (0..width).map(|i| {
+                let mut brightest: f64 = 0.0;
+                for row in &luminance[j.saturating_sub(1)..(j + 2).min(height)] {
+                    for di in [width - 1, 0, 1] {
+                        brightest = brightest.max(row[(i + di) % width]);
+                    }
+                }
+                brightest * sin_theta
+            }).collect()
+        }).collect();
+
+        Self { texture, intensity: 1.0, rotation: 0.0, distribution: Distribution2D::new(rows) }
+    }
+
+    pub fn load(filepath: String) -> Self {
+        Environment::new(ImageTexture::load(filepath))
+    }
+
+    pub fn texture(&self) -> &ImageTexture {
+        &self.texture
+    }
+
+    // Rotates a world space direction into the map's frame
+    fn map_direction(&self, d: Vec3<f64>) -> Vec3<f64> {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        Vec3::new(d.x*cos + d.z*sin, d.y, d.z*cos - d.x*sin)
+    }
+
+    // Rotates a direction in the map's frame back into world space
+    fn world_direction(&self, d: Vec3<f64>) -> Vec3<f64> {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        Vec3::new(d.x*cos - d.z*sin, d.y, d.z*cos + d.x*sin)
+    }
+}
+
+impl Background for Environment {
+    fn color(&self, direction: Vec3<f64>) -> Color {
+        let (u, v) = uv_from_direction(self.map_direction(direction.normalized()));
+        self.texture.color(&TexCoord::uv(u, v)) * self.intensity
+    }
+
+    // Picks directions towards the bright parts of the map
+    fn sample(&self) -> Option<(Vec3<f64>, Color, f64)> {
+        let mut rng = thread_rng();
+        let (u, v, pdf) = self.distribution.sample(rng.gen(), rng.gen());
+
+        let sin_theta = (PI * v).sin();
+        if pdf == 0.0 || sin_theta == 0.0 {
+            return None;
+        }
+        let direction = self.world_direction(direction_from_uv(u, v));
+
+        Some((direction, self.color(direction), pdf / (2.0 * PI * PI * sin_theta)))
+    }
+
+    fn pdf(&self, direction: Vec3<f64>) -> f64 {
+        let d = self.map_direction(direction.normalized());
+        let (u, v) = uv_from_direction(d);
+        let sin_theta = (1.0 - d.y*d.y).max(0.0).sqrt();
+        if sin_theta == 0.0 {
+            return 0.0;
+        }
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+// Luminance at each texel centre, clamped to be non-negative, row j at
+// v = (j + 0.5) / height
+pub(crate) fn texel_luminance(texture: &ImageTexture) -> Vec<Vec<f64>> {
+    let (width, height) = (texture.width() as usize, texture.height() as usize);
+    (0..height).map(|j| {
+        let v = (j as f64 + 0.5) / height as f64;
+        (0..width).map(|i| {
+            let u = (i as f64 + 0.5) / width as f64;
+            spectrum::luminance(texture.color(&TexCoord::uv(u, v))).max(0.0)
+        }).collect()
+    }).collect()
+}
+
+// Map coordinates of a direction, v running from straight down to
+// straight up. The middle of the map is +x and u increases towards +z,
+// which is to the right when looking out along +x from inside, so maps
+// don't come out mirrored.
+pub(crate) fn uv_from_direction(d: Vec3<f64>) -> (f64, f64) {
+    let theta = (-d.y).clamp(-1.0, 1.0).acos();
+    let phi = d.z.atan2(d.x) + PI;
+    (phi / (2.0 * PI), theta / PI)
+}
+
+// Inverse of uv_from_direction
+pub(crate) fn direction_from_uv(u: f64, v: f64) -> Vec3<f64> {
+    let theta = PI * v;
+    let phi = 2.0 * PI * u;
+    Vec3::new(-phi.cos() * theta.sin(), -theta.cos(), -phi.sin() * theta.sin())
+}
+
+// Piecewise constant distribution over [0, 1)
+#[derive(Clone)]
+struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    // Average of func
+    integral: f64,
+}
+
+impl Distribution1D {
+    fn new(func: Vec<f64>) -> Self {
+        let n = func.len();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 0..n {
+            cdf[i + 1] = cdf[i] + func[i] / n as f64;
+        }
+        let integral = cdf[n];
+        for (i, c) in cdf.iter_mut().enumerate() {
+            // All zero functions fall back to uniform
+            *c = if integral > 0.0 { *c / integral } else { i as f64 / n as f64 };
+        }
+        Self { func, cdf, integral }
+    }
+
+    // Maps a uniform random number to a sample, its pdf and bucket
+    fn sample(&self, r: f64) -> (f64, f64, usize) {
+        let n = self.func.len();
+        let i = self.cdf.partition_point(|&c| c <= r).saturating_sub(1).min(n - 1);
+        let width = self.cdf[i + 1] - self.cdf[i];
+        let offset = if width > 0.0 { (r - self.cdf[i]) / width } else { 0.0 };
+        ((i as f64 + offset) / n as f64, self.pdf(i), i)
+    }
+
+    fn pdf(&self, i: usize) -> f64 {
+        if self.integral > 0.0 { self.func[i] / self.integral } else { 1.0 }
+    }
+}
+
+// Piecewise constant distribution over [0, 1)², picking v from the row
+// totals then u within that row
+#[derive(Clone)]
+pub(crate) struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub(crate) fn new(rows: Vec<Vec<f64>>) -> Self {
+        let rows: Vec<Distribution1D> = rows.into_iter().map(Distribution1D::new).collect();
+        let marginal = Distribution1D::new(rows.iter().map(|row| row.integral).collect());
+        Self { rows, marginal }
+    }
+
+    // Returns (u, v, pdf)
+    pub(crate) fn sample(&self, r1: f64, r2: f64) -> (f64, f64, f64) {
+        let (v, pdf_v, j) = self.marginal.sample(r1);
+        let (u, pdf_u, _) = self.rows[j].sample(r2);
+        (u, v, pdf_u * pdf_v)
+    }
+
+    fn pdf(&self, u: f64, v: f64) -> f64 {
+        let j = ((v * self.rows.len() as f64) as usize).min(self.rows.len() - 1);
+        let row = &self.rows[j];
+        let i = ((u * row.func.len() as f64) as usize).min(row.func.len() - 1);
+        self.marginal.pdf(j) * row.pdf(i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 4x1 map, bright in the first column and dark elsewhere
+    fn environment() -> Environment {
+        let mut texels = vec![0.0; 12];
+        texels[..3].copy_from_slice(&[10.0, 10.0, 10.0]);
+        let mut environment = Environment::new(ImageTexture::new(texels, vec![], 4, 1));
+        environment.texture.filter = Filter::Nearest;
+        environment
+    }
+
+    // Centre of the first and third columns
+    fn bright() -> Vec3<f64> {
+        Vec3::new(-1.0, 0.0, -1.0)
+    }
+
+    fn dark() -> Vec3<f64> {
+        Vec3::new(1.0, 0.0, 1.0)
+    }
+
+    #[test]
+    fn test_direction_from_uv() {
+        for (u, v) in [(0.1, 0.2), (0.5, 0.5), (0.8, 0.9)] {
+            let (u2, v2) = uv_from_direction(direction_from_uv(u, v));
+            assert!((u - u2).abs() < 1e-9 && (v - v2).abs() < 1e-9);
+        }
+
+        // Looking out along +x with +y up, the right of the map is on the
+        // right, +z
+        let (u, v) = uv_from_direction(Vec3::new(1.0, 0.0, 0.0));
+        assert!((u - 0.5).abs() < 1e-9 && (v - 0.5).abs() < 1e-9);
+        assert!((uv_from_direction(Vec3::new(0.0, 0.0, 1.0)).0 - 0.75).abs() < 1e-9);
+        assert!((uv_from_direction(Vec3::new(0.0, 1.0, 0.0)).1 - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_unclamped() {
+        let environment = environment();
+        assert_eq!(environment.color(bright()).x, 10.0);
+    }
+
+    #[test]
+    fn test_intensity_rotation() {
+        let mut environment = environment();
+        environment.intensity = 0.5;
+        assert_eq!(environment.color(bright()).x, 5.0);
+
+        // Half a turn swaps the bright and dark columns
+        environment.rotation = 180.0;
+        assert_eq!(environment.color(bright()).x, 0.0);
+        assert_eq!(environment.color(dark()).x, 5.0);
+    }
+
+    #[test]
+    fn test_poles() {
+        // 1x2 map, white sky over black ground. Looking straight up or down
+        // doesn't blend in the opposite pole.
+        let environment = Environment::new(ImageTexture::new(vec![1.0, 1.0, 1.0, 0.0, 0.0, 0.0], vec![], 1, 2));
+        assert_eq!(environment.color(Vec3::new(0.0, 1.0, 0.0)).x, 1.0);
+        assert_eq!(environment.color(Vec3::new(0.0, -1.0, 0.0)).x, 0.0);
+    }
+
+    #[test]
+    fn test_sample_pdf() {
+        let environment = environment();
+        for _ in 0..100 {
+            let (direction, _, pdf) = environment.sample().expect("expected a sample");
+            assert!((environment.pdf(direction) - pdf).abs() < 1e-9 * pdf);
+        }
+        // Too far from the bright column to pick up any of its light
+        assert_eq!(environment.pdf(dark()), 0.0);
+        assert!(environment.pdf(bright()) > 0.0);
+    }
+
+    #[test]
+    fn test_pdf_normalized() {
+        let mut environment = environment();
+        environment.rotation = 30.0;
+        // Integrate the pdf over the sphere in latitude-longitude steps
+        let (nu, nv) = (200, 100);
+        let mut total = 0.0;
+        for j in 0..nv {
+            let theta = PI * (j as f64 + 0.5) / nv as f64;
+            for i in 0..nu {
+                let phi = 2.0 * PI * (i as f64 + 0.5) / nu as f64;
+                let d = Vec3::new(phi.cos()*theta.sin(), theta.cos(), phi.sin()*theta.sin());
+                total += environment.pdf(d) * theta.sin() * (PI / nv as f64) * (2.0 * PI / nu as f64);
+            }
+        }
+        assert!((total - 1.0).abs() < 1e-2);
+    }
+}
@@ -5,9 +5,13 @@ pub mod camera;
 pub mod scene;
 pub mod material;
 pub mod texture;
+pub mod spectrum;
+pub mod background;
+pub mod environment;
+pub mod light;
+pub mod physical_sky;
 
-use vector::Vec3;
-use camera::Camera;
+use vector::{Vec3, Color};
 use crate::scene::Scene;
 
 use rand::{thread_rng, Rng};
@@ -15,35 +19,59 @@ use num::clamp;
 use rayon::prelude::*;
 use indicatif::ProgressBar;
 
-pub fn render(scene: &Scene, image_width: i32, samples_per_pixel: i32, max_depth: i32) -> Vec<u8> {
+// In spectral mode every sample carries a single random wavelength
+// which is converted back to RGB when accumulated. White balance is
+// given as the color temperature in Kelvin that should appear white.
+pub fn render(scene: &Scene, image_width: i32, samples_per_pixel: i32, max_depth: i32,
+    spectral: bool, white_balance: Option<f64>) -> Vec<u8> {
     let cam = &scene.camera;
-    let image_height = ((image_width as f64) / cam.aspect_ratio) as i32;
+    let white_balance = match white_balance {
+        Some(temperature) => spectrum::white_balance(temperature),
+        None => Color::one(),
+    };
+    let image_height = ((image_width as f64) / cam.aspect_ratio()) as i32;
 
     let mut image_buffer: Vec<u8> = vec![0; (image_width * image_height * 3) as usize];
     let rows: Vec<(usize, &mut [u8])> = image_buffer.chunks_mut((image_width * 3) as usize).rev().enumerate().collect();
 
     let bar = ProgressBar::new(rows.len().try_into().unwrap());
 
+    // Each sample only covers part of the pixel
+    let differential_scale = (1.0 / (samples_per_pixel as f64).sqrt()).max(0.125);
+
     rows.into_par_iter().for_each(|(j, row)| {
         let mut rng = thread_rng();
         for i in 0..image_width {
             let mut pixel_color_sum = Vec3::zero();
-            for _s in 0..samples_per_pixel {
+            for s in 0..samples_per_pixel {
                 let u = (i as f64 + rng.gen_range(0.0..1.0)) / ((image_width as f64) - 1.0);
                 let v = (j as f64 + rng.gen_range(0.0..1.0)) / ((image_height as f64) - 1.0);
-                let r = cam.get_ray(u, v);
-                pixel_color_sum += r.color(&scene, max_depth);
+                let Some(mut r) = cam.get_ray_differential(u, v,
+                    1.0 / ((image_width as f64) - 1.0), 1.0 / ((image_height as f64) - 1.0)) else {
+                    continue;
+                };
+                r.scale_differentials(differential_scale);
+                if spectral {
+                    // Stratified over the pixel's samples to cut color noise
+                    let xi = (s as f64 + rng.gen_range(0.0..1.0)) / samples_per_pixel as f64;
+                    let wavelength = spectrum::sample_wavelength(xi);
+                    r.wavelength = Some(wavelength);
+                    pixel_color_sum += spectrum::wavelength_to_rgb(wavelength) * r.color(scene, max_depth);
+                } else {
+                    pixel_color_sum += r.color(scene, max_depth);
+                }
             }
 
             let scale = 1.0 / samples_per_pixel as f64;
             pixel_color_sum.x *= scale;
             pixel_color_sum.y *= scale;
             pixel_color_sum.z *= scale;
+            pixel_color_sum *= white_balance;
 
-
-            row[((i*3)+0) as usize] = (256.0 * clamp(pixel_color_sum.x.sqrt(), 0.0, 0.999)) as u8;
-            row[((i*3)+1) as usize] = (256.0 * clamp(pixel_color_sum.y.sqrt(), 0.0, 0.999)) as u8;
-            row[((i*3)+2) as usize] = (256.0 * clamp(pixel_color_sum.z.sqrt(), 0.0, 0.999)) as u8;
+            // Rendering is done in linear light, encode as sRGB for output
+            row[(i*3) as usize] = (256.0 * clamp(spectrum::linear_to_srgb(pixel_color_sum.x), 0.0, 0.999)) as u8;
+            row[((i*3)+1) as usize] = (256.0 * clamp(spectrum::linear_to_srgb(pixel_color_sum.y), 0.0, 0.999)) as u8;
+            row[((i*3)+2) as usize] = (256.0 * clamp(spectrum::linear_to_srgb(pixel_color_sum.z), 0.0, 0.999)) as u8;
         }
         bar.inc(1);
     });
@@ -0,0 +1,18 @@
+use crate::vector::{Vec3, Color};
+
+// Distant directional light such as the sun. Being infinitely far away
+// and infinitely small it casts perfectly sharp shadows, and can only
+// be reached by sampling it directly.
+#[derive(Copy, Clone, Debug)]
+pub struct Sun {
+    // Unit vector pointing towards the sun
+    pub direction: Vec3<f64>,
+    // Irradiance on a surface facing the sun
+    pub irradiance: Color,
+}
+
+impl Sun {
+    pub fn new(direction: Vec3<f64>, irradiance: Color) -> Self {
+        Self { direction: direction.normalized(), irradiance }
+    }
+}
@@ -16,6 +16,23 @@ struct Args {
 
     #[arg(short, long, default_value_t = 20)]
     max_depth: i32,
+
+    // Trace single wavelengths per sample (enables dispersion)
+    #[arg(long)]
+    spectral: bool,
+
+    // Color temperature in Kelvin that should appear white
+    #[arg(short, long, value_parser = parse_temperature)]
+    white_balance: Option<f64>,
+}
+
+fn parse_temperature(s: &str) -> Result<f64, String> {
+    let temperature: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if temperature > 0.0 && temperature.is_finite() {
+        Ok(temperature)
+    } else {
+        Err(format!("{temperature} is not a positive temperature in Kelvin"))
+    }
 }
 
 fn main() {
@@ -31,12 +48,14 @@ fn main() {
         &scene,
         args.image_width,
         args.samples_per_pixel,
-        args.max_depth
+        args.max_depth,
+        args.spectral,
+        args.white_balance
     );
 
     println!("Time elapsed: {}ms", start.elapsed().as_millis());
 
-    let image_height = ((args.image_width as f64) / scene.camera.aspect_ratio) as i32;
+    let image_height = ((args.image_width as f64) / scene.camera.aspect_ratio()) as i32;
     let result = image::save_buffer(
         file_path,
         &image_buffer[..],
@@ -45,9 +64,8 @@ fn main() {
         image::ColorType::Rgb8
     );
 
-    match result {
-        Err(e) => eprintln!("Error saving image: {e:?}"),
-        _ => (),
+    if let Err(e) = result {
+        eprintln!("Error saving image: {e:?}");
     }
     println!("Done!");
 }
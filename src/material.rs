@@ -1,24 +1,164 @@
 pub mod lambertian;
+pub mod oren_nayar;
 pub mod metal;
 pub mod dielectric;
 pub mod diffuse_light;
+pub mod subsurface;
+pub mod principled;
+pub mod mix;
+pub mod coated;
+pub mod cutout;
+pub mod thin_film;
+pub mod normal_map;
+pub mod bump_map;
 pub use self::lambertian::Lambertian;
+pub use self::oren_nayar::OrenNayar;
 pub use self::metal::Metal;
-pub use self::dielectric::Dielectric;
+pub use self::dielectric::{Dielectric, Dispersion};
 pub use self::diffuse_light::DiffuseLight;
+pub use self::subsurface::Subsurface;
+pub use self::principled::Principled;
+pub use self::mix::Mix;
+pub use self::coated::Coated;
+pub use self::cutout::Cutout;
+pub use self::thin_film::ThinFilm;
+pub use self::normal_map::NormalMap;
+pub use self::bump_map::BumpMap;
 
-use crate::vector::Color;
+use crate::vector::{Vec3, Color};
 use crate::ray::Ray;
 use crate::shape::HitRecord;
 
 pub struct Scatter {
     pub scattered: Ray,
-    pub attenuation: Color
+    pub attenuation: Color,
+    // Sampled from a perfectly sharp lobe (mirror reflection, refraction
+    // through smooth glass) that eval leaves out
+    pub delta: bool,
 }
 
 pub trait Material {
     fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<Scatter>;
-    fn emitted(&self, _u: f64, _v: f64) -> Color {
+    fn emitted(&self, _ray: &Ray, _rec: &HitRecord) -> Color {
         Color::zero()
     }
+
+    // Probability that a ray hitting the surface interacts with it,
+    // rays passing through transparent parts continue unchanged
+    fn opacity(&self, _rec: &HitRecord) -> f64 {
+        1.0
+    }
+
+    // BSDF times cosine for light leaving along `direction`, and the pdf
+    // scatter samples that direction with, leaving out delta lobes. Lets
+    // lights be sampled directly. None for materials with nothing but
+    // delta lobes.
+    fn eval(&self, _ray: &Ray, _rec: &HitRecord, _direction: Vec3<f64>) -> Option<(Color, f64)> {
+        None
+    }
+}
+
+// Adds up the evaluations of two lobes, either of which may be missing
+pub(crate) fn add_eval(a: Option<(Color, f64)>, b: Option<(Color, f64)>) -> Option<(Color, f64)> {
+    match (a, b) {
+        (Some((f1, pdf1)), Some((f2, pdf2))) => Some((f1 + f2, pdf1 + pdf2)),
+        (a, b) => a.or(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::{Hittable, Plane, Sphere};
+    use std::f64::consts::PI;
+
+    // Checks eval against scatter for a hit: integrated over the sphere,
+    // eval's value has to match the average attenuation of the samples
+    // scatter takes from lobes other than delta ones, and its pdf the
+    // odds of taking such a sample
+    fn check_eval(name: &str, ray: &Ray, rec: &HitRecord) {
+        let (nu, nv) = (400, 200);
+        let mut eval_albedo = Color::zero();
+        let mut eval_pdf = 0.0;
+        for j in 0..nv {
+            let theta = PI * (j as f64 + 0.5) / nv as f64;
+            let area = theta.sin() * (PI / nv as f64) * (2.0 * PI / nu as f64);
+            for i in 0..nu {
+                let phi = 2.0 * PI * (i as f64 + 0.5) / nu as f64;
+                let d = Vec3::new(phi.cos()*theta.sin(), theta.cos(), phi.sin()*theta.sin());
+                let (f, pdf) = rec.material.eval(ray, rec, d).unwrap();
+                eval_albedo += f * area;
+                eval_pdf += pdf * area;
+            }
+        }
+
+        let n = 100000;
+        let mut albedo = Color::zero();
+        let mut sampled = 0;
+        for _ in 0..n {
+            if let Some(scatter) = rec.material.scatter(ray, rec) {
+                if !scatter.delta {
+                    albedo += scatter.attenuation;
+                    sampled += 1;
+                }
+            }
+        }
+        let albedo = albedo / n as f64;
+        let sampled = sampled as f64 / n as f64;
+
+        assert!((eval_pdf - sampled).abs() < 0.02, "{name}: pdf integrates to {eval_pdf}, sampled {sampled}");
+        for (a, b) in [(eval_albedo.x, albedo.x), (eval_albedo.y, albedo.y), (eval_albedo.z, albedo.z)] {
+            assert!((a - b).abs() < 0.02, "{name}: eval albedo {a}, sampled {b}");
+        }
+    }
+
+    fn check_plane(name: &str, material: impl Material + Send + Sync) {
+        let ground = Plane::ground(material);
+        let (ray, rec) = ground.hit_from(Vec3::new(-0.6, 1.0, 0.2));
+        check_eval(name, &ray, &rec);
+    }
+
+    #[test]
+    fn test_eval_matches_scatter() {
+        check_plane("lambertian", Lambertian { albedo: Color::new(0.8, 0.5, 0.2) });
+        check_plane("fuzzy metal", Metal::new(Color::new(0.9, 0.6, 0.3), 0.4));
+        check_plane("very fuzzy metal", Metal::new(Color::one(), 1.5));
+        check_plane("fuzzy coat", Coated {
+            base: Lambertian { albedo: Color::new(0.5, 0.5, 0.5) },
+            ir: 1.5,
+            fuzz: 0.3,
+        });
+        check_plane("smooth coat", Coated::new(OrenNayar { albedo: Color::new(0.2, 0.4, 0.6), sigma: 0.3 }, 1.5));
+        check_plane("mix", Mix {
+            first: Lambertian { albedo: Color::one() },
+            second: Dielectric::new(1.5),
+            weight: 0.3,
+        });
+
+        check_plane("principled", Principled::new(Color::new(0.8, 0.4, 0.2)));
+        let mut metal = Principled::new(Color::new(0.9, 0.7, 0.4));
+        metal.metallic = std::sync::Arc::new(1.0);
+        metal.roughness = std::sync::Arc::new(0.4);
+        check_plane("principled metal", metal);
+        let mut coated = Principled::new(Color::new(0.3, 0.5, 0.8));
+        coated.clearcoat = std::sync::Arc::new(1.0);
+        coated.clearcoat_roughness = std::sync::Arc::new(0.3);
+        coated.sheen = std::sync::Arc::new(0.5);
+        check_plane("principled clearcoat", coated);
+        let mut glass = Principled::new(Color::new(0.9, 0.9, 0.8));
+        glass.transmission = std::sync::Arc::new(1.0);
+        glass.roughness = std::sync::Arc::new(0.4);
+        check_plane("principled glass", glass.clone());
+
+        // Leaving the inside of rough glass and of a scattering medium
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.3, 0.8, -0.2));
+        let sphere = Sphere::new(Vec3::zero(), 1.0, glass);
+        check_eval("principled glass exit", &ray, &sphere.hit(&ray, (0.001, f64::INFINITY)).unwrap());
+        let sphere = Sphere::new(Vec3::zero(), 1.0, Subsurface {
+            albedo: Color::one(),
+            mean_free_path: Color::new(2.0, 1.0, 0.5),
+            ir: 1.3,
+        });
+        check_eval("subsurface exit", &ray, &sphere.hit(&ray, (0.001, f64::INFINITY)).unwrap());
+    }
 }
@@ -0,0 +1,128 @@
+use crate::material::{Scatter, Material};
+use crate::vector::{Vec3, Color};
+use crate::ray::Ray;
+use crate::shape::HitRecord;
+use crate::texture::Texture;
+
+// Perturbs the shading normal of a material as if the surface were
+// displaced along its normal by a height texture. The hit point is left
+// on the actual geometry. Image height maps need bilinear or bicubic
+// filtering, nearest is flat within a texel so its slope is either zero
+// or a spike at the texel edges.
+#[derive(Clone)]
+pub struct BumpMap<M: Material, T: Texture> {
+    pub material: M,
+    pub height: T,
+    // Displacement in scene units for a height of 1
+    pub scale: f64,
+}
+
+// Step in texture space for the finite difference height derivatives,
+// used when the ray carries no differentials
+const DELTA: f64 = 0.0005;
+
+// About half a pixel's footprint along one texture axis, so the height
+// is differenced at the scale the image is seen at
+fn step(dx: f64, dy: f64) -> f64 {
+    let d = 0.5 * (dx.abs() + dy.abs());
+    if d > 0.0 { d } else { DELTA }
+}
+
+impl<M: Material, T: Texture> BumpMap<M, T> {
+    fn shade<'a>(&self, rec: &HitRecord<'a>) -> HitRecord<'a> {
+        let coord = rec.tex_coord();
+        let h = self.height.value(&coord);
+
+        // Step the hit point along with (u, v) so solid textures see it move
+        let (step_u, step_v) = (step(coord.duvdx.0, coord.duvdy.0), step(coord.duvdx.1, coord.duvdy.1));
+        let mut du = coord;
+        du.u += step_u;
+        du.point += rec.dpdu * step_u;
+        du.local_point += rec.dpdu * step_u;
+        let mut dv = coord;
+        dv.v += step_v;
+        dv.point += rec.dpdv * step_v;
+        dv.local_point += rec.dpdv * step_v;
+
+        let dhdu = (self.height.value(&du) - h) / step_u;
+        let dhdv = (self.height.value(&dv) - h) / step_v;
+
+        let n = rec.shading_normal;
+        let dpdu = rec.dpdu + n*(dhdu*self.scale);
+        let dpdv = rec.dpdv + n*(dhdv*self.scale);
+        let bumped = dpdu.cross(dpdv);
+
+        let mut shaded = *rec;
+        if !bumped.near_zero() {
+            let bumped = bumped.normalized();
+            // Keep the normal on the same side as the geometric one
+            shaded.shading_normal = if bumped.dot(rec.normal) < 0.0 { -bumped } else { bumped };
+        }
+        shaded
+    }
+}
+
+impl<M: Material, T: Texture> Material for BumpMap<M, T> {
+    fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> Option<Scatter> {
+        self.material.scatter(ray_in, &self.shade(rec))
+    }
+
+    fn emitted(&self, ray_in: &Ray, rec: &HitRecord) -> Color {
+        self.material.emitted(ray_in, &self.shade(rec))
+    }
+
+    fn opacity(&self, rec: &HitRecord) -> f64 {
+        self.material.opacity(rec)
+    }
+
+    fn eval(&self, ray_in: &Ray, rec: &HitRecord, direction: Vec3<f64>) -> Option<(Color, f64)> {
+        self.material.eval(ray_in, &self.shade(rec), direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::shape::{Plane, Differentials};
+    use crate::texture::UVTexture;
+
+    #[test]
+    fn test_ramp() {
+        // Height rising with u at the same rate as the surface, a 45 degree
+        // slope whose normal leans back towards -u
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let ground = Plane::ground(BumpMap {
+            material: Lambertian { albedo: Color::one() },
+            height: UVTexture,
+            scale: 1.0,
+        });
+        let (_, mut rec) = ground.hit_from(up);
+        let tilt = std::f64::consts::FRAC_1_SQRT_2;
+
+        // With and without a pixel footprint to size the step
+        for differentials in [None, Some(Differentials {
+            dpdx: rec.dpdu * 0.1,
+            dpdy: rec.dpdv * 0.1,
+            dudx: 0.1,
+            dvdx: 0.0,
+            dudy: 0.0,
+            dvdy: 0.1,
+        })] {
+            rec.differentials = differentials;
+            let shaded = ground.material.shade(&rec);
+            assert_eq!(shaded.normal, up);
+            assert!((shaded.shading_normal.dot(up) - tilt).abs() < 1e-9);
+            assert!((shaded.shading_normal.dot(rec.dpdu.normalized()) + tilt).abs() < 1e-9);
+        }
+
+        // Scale sets the slope, 0 leaves the surface flat
+        let flat = Plane::ground(BumpMap {
+            material: Lambertian { albedo: Color::one() },
+            height: UVTexture,
+            scale: 0.0,
+        });
+        let (_, rec) = flat.hit_from(up);
+        assert!((flat.material.shade(&rec).shading_normal - up).length() < 1e-12);
+    }
+}
@@ -0,0 +1,114 @@
+use crate::material::{Scatter, Material, add_eval};
+use crate::material::dielectric::reflectance;
+use crate::material::metal::fuzzy_reflection_pdf;
+use crate::vector::{Vec3, Color};
+use crate::ray::Ray;
+use crate::shape::HitRecord;
+use crate::texture::Texture;
+use rand::{thread_rng, Rng};
+
+// Clear dielectric coating (varnish, lacquer) over a base material.
+// Light reflects off the coat with Fresnel probability, the rest
+// reaches the base material.
+#[derive(Clone)]
+pub struct Coated<M: Material, F: Texture = f64> {
+    pub base: M,
+    pub ir: f64,
+    pub fuzz: F,
+}
+
+impl<M: Material> Coated<M> {
+    pub fn new(base: M, ir: f64) -> Self {
+        Self { base, ir, fuzz: 0.0 }
+    }
+}
+
+impl<M: Material, F: Texture> Material for Coated<M, F> {
+    fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> Option<Scatter> {
+        if !rec.front_face {
+            return self.base.scatter(ray_in, rec);
+        }
+
+        let unit_direction = ray_in.direction.normalized();
+        let cos_theta = (-unit_direction).dot(rec.shading_normal).min(1.0);
+
+        if reflectance(cos_theta, self.ir) > thread_rng().gen() {
+            let fuzz = self.fuzz.value(&rec.tex_coord());
+            let reflected = unit_direction.reflect(rec.shading_normal);
+            let scattered = ray_in.reflected(rec, reflected + Vec3::random_in_unit_sphere()*fuzz);
+
+            if scattered.direction.dot(rec.normal) > 0.0 {
+                Some( Scatter {
+                    scattered,
+                    attenuation: Color::one(),
+                    delta: fuzz <= 0.0,
+                })
+            } else {
+                None
+            }
+        } else {
+            self.base.scatter(ray_in, rec)
+        }
+    }
+
+    fn emitted(&self, ray_in: &Ray, rec: &HitRecord) -> Color {
+        self.base.emitted(ray_in, rec)
+    }
+
+    fn opacity(&self, rec: &HitRecord) -> f64 {
+        self.base.opacity(rec)
+    }
+
+    // The base weighted by the light getting through the coat, plus the
+    // coat itself when it's fuzzy
+    fn eval(&self, ray_in: &Ray, rec: &HitRecord, direction: Vec3<f64>) -> Option<(Color, f64)> {
+        if !rec.front_face {
+            return self.base.eval(ray_in, rec, direction);
+        }
+
+        let unit_direction = ray_in.direction.normalized();
+        let cos_theta = (-unit_direction).dot(rec.shading_normal).min(1.0);
+        let coat = reflectance(cos_theta, self.ir);
+
+        let base = self.base.eval(ray_in, rec, direction)
+            .map(|(f, pdf)| (f * (1.0 - coat), pdf * (1.0 - coat)));
+
+        let fuzz = self.fuzz.value(&rec.tex_coord());
+        let reflection = if fuzz <= 0.0 {
+            None
+        } else if direction.dot(rec.normal) <= 0.0 {
+            Some((Color::zero(), 0.0))
+        } else {
+            let reflected = unit_direction.reflect(rec.shading_normal);
+            let pdf = coat * fuzzy_reflection_pdf(reflected, fuzz, direction.normalized());
+            Some((Color::one() * pdf, pdf))
+        };
+
+        add_eval(base, reflection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::shape::Plane;
+
+    #[test]
+    fn test_coat_reflectance() {
+        // Over a black base only the coat reflects, about 4% head on for
+        // an index of 1.5 and more at grazing angles
+        let ground = Plane::ground(Coated::new(Lambertian { albedo: Color::zero() }, 1.5));
+        let n = 40000;
+        for (x, expected) in [(0.0, reflectance(1.0, 1.5)), (4.0, reflectance(1.0 / 17f64.sqrt(), 1.5))] {
+            let (ray, rec) = ground.hit_from(Vec3::new(-x, 1.0, 0.0));
+            let mut sum = 0.0;
+            for _ in 0..n {
+                if let Some(scatter) = rec.material.scatter(&ray, &rec) {
+                    sum += scatter.attenuation.x;
+                }
+            }
+            assert!((sum / n as f64 - expected).abs() < 0.01, "{} vs {expected}", sum / n as f64);
+        }
+    }
+}
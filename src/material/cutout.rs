@@ -0,0 +1,74 @@
+use crate::material::{Scatter, Material};
+use crate::vector::{Vec3, Color};
+use crate::ray::Ray;
+use crate::shape::HitRecord;
+use crate::texture::Texture;
+
+// Masks a material by the alpha channel of a texture (foliage, decals).
+// Hits with alpha below the threshold are ignored entirely, partial
+// alpha above it is treated as stochastic transparency.
+#[derive(Clone)]
+pub struct Cutout<M: Material, T: Texture> {
+    pub material: M,
+    pub mask: T,
+    pub threshold: f64,
+}
+
+impl<M: Material, T: Texture> Material for Cutout<M, T> {
+    fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> Option<Scatter> {
+        self.material.scatter(ray_in, rec)
+    }
+
+    fn emitted(&self, ray_in: &Ray, rec: &HitRecord) -> Color {
+        self.material.emitted(ray_in, rec)
+    }
+
+    fn opacity(&self, rec: &HitRecord) -> f64 {
+        let alpha = self.mask.alpha(&rec.tex_coord());
+        if alpha < self.threshold {
+            0.0
+        } else {
+            alpha * self.material.opacity(rec)
+        }
+    }
+
+    fn eval(&self, ray_in: &Ray, rec: &HitRecord, direction: Vec3<f64>) -> Option<(Color, f64)> {
+        self.material.eval(ray_in, rec, direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::shape::Plane;
+    use crate::texture::ImageTexture;
+
+    // 1x1 white mask with the given alpha
+    fn mask(alpha: f32) -> ImageTexture {
+        ImageTexture::new(vec![1.0; 3], vec![alpha], 1, 1)
+    }
+
+    #[test]
+    fn test_opacity() {
+        // Cut away below the threshold, partially transparent above it
+        for (alpha, expected) in [(0.0, 0.0), (0.3, 0.0), (0.7, 0.7), (1.0, 1.0)] {
+            let ground = Plane::ground(Cutout {
+                material: Lambertian { albedo: Color::one() },
+                mask: mask(alpha),
+                threshold: 0.5,
+            });
+            let (_, rec) = ground.hit_from(Vec3::new(0.0, 1.0, 0.0));
+            assert!((rec.material.opacity(&rec) - expected).abs() < 1e-6, "alpha {alpha}");
+        }
+
+        // Masks without alpha are opaque
+        let ground = Plane::ground(Cutout {
+            material: Lambertian { albedo: Color::one() },
+            mask: Color::one(),
+            threshold: 0.5,
+        });
+        let (_, rec) = ground.hit_from(Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(rec.material.opacity(&rec), 1.0);
+    }
+}
@@ -2,37 +2,173 @@ use crate::material::{Scatter, Material};
 use crate::vector::Color;
 use crate::ray::Ray;
 use crate::shape::HitRecord;
+use crate::texture::Texture;
+use crate::material::ThinFilm;
 use rand::{thread_rng, Rng};
 
 #[derive(Clone)]
-pub struct Dielectric {
-    pub ir: f64
+pub struct Dielectric<I: Texture = f64, A: Texture = Color> {
+    pub ir: I,
+    // Beer-Lambert absorption coefficient per unit distance travelled
+    // inside the volume (zero for clear glass)
+    pub absorption: A,
+    // Wavelength dependent IOR used instead of ir in spectral mode
+    pub dispersion: Option<Dispersion>,
+    // Optional coating giving iridescent reflections (soap bubbles,
+    // coated lenses)
+    pub thin_film: Option<ThinFilm>,
 }
 
-impl Material for Dielectric {
+// Models of how the index of refraction varies with wavelength
+#[derive(Copy, Clone, Debug)]
+pub enum Dispersion {
+    // n = a + b/λ², λ in micrometres
+    Cauchy { a: f64, b: f64 },
+    // n² = 1 + Σ bᵢλ²/(λ² - cᵢ), λ in micrometres
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Dispersion {
+    pub const BK7: Dispersion = Dispersion::Sellmeier {
+        b: [1.03961212, 0.231792344, 1.01046945],
+        c: [0.00600069867, 0.0200179144, 103.560653],
+    };
+
+    pub const DIAMOND: Dispersion = Dispersion::Sellmeier {
+        b: [0.3306, 4.3356, 0.0],
+        c: [0.030625, 0.011236, 0.0],
+    };
+
+    // Index of refraction at a wavelength given in nanometres
+    pub fn ior(&self, wavelength: f64) -> f64 {
+        let l = wavelength / 1000.0;
+        let l2 = l * l;
+        match self {
+            Dispersion::Cauchy { a, b } => a + b / l2,
+            Dispersion::Sellmeier { b, c } => {
+                (1.0 + (0..3).map(|i| b[i] * l2 / (l2 - c[i])).sum::<f64>()).sqrt()
+            },
+        }
+    }
+}
+
+impl<I: Texture> Dielectric<I> {
+    pub fn new(ir: I) -> Self {
+        Self { ir, absorption: Color::zero(), dispersion: None, thin_film: None }
+    }
+
+    // Tinted dielectric where light travelling `distance` units through the
+    // volume is left with `transmittance` of its original color. Fully
+    // opaque channels are clamped to a tiny transmittance to keep the
+    // absorption finite.
+    pub fn tinted(ir: I, transmittance: Color, distance: f64) -> Self {
+        assert!(distance > 0.0, "tinted distance must be positive, got {distance}");
+        let coefficient = |t: f64| -t.clamp(1e-6, 1.0).ln() / distance;
+        let absorption = Color::new(
+            coefficient(transmittance.x),
+            coefficient(transmittance.y),
+            coefficient(transmittance.z)
+        );
+        Self { ir, absorption, dispersion: None, thin_film: None }
+    }
+}
+
+impl Dielectric {
+    // Dispersive dielectric, outside of spectral mode it uses the IOR
+    // at the sodium d-line (587.6nm)
+    pub fn dispersive(dispersion: Dispersion) -> Self {
+        Self {
+            ir: dispersion.ior(587.6),
+            absorption: Color::zero(),
+            dispersion: Some(dispersion),
+            thin_film: None
+        }
+    }
+}
+
+impl<I: Texture, A: Texture> Material for Dielectric<I, A> {
     fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> Option<Scatter> {
-        let refraction_ratio = if rec.front_face { 1.0/self.ir } else { self.ir };
-        
+        let ir = match (self.dispersion, ray_in.wavelength) {
+            (Some(dispersion), Some(wavelength)) => dispersion.ior(wavelength),
+            _ => self.ir.value(&rec.tex_coord()),
+        };
+        let refraction_ratio = if rec.front_face { 1.0/ir } else { ir };
+
         let unit_direction = ray_in.direction.normalized();
-        let cos_theta = (-unit_direction).dot(rec.normal).min(1.0);
+        let cos_theta = (-unit_direction).dot(rec.shading_normal).min(1.0);
         let sin_theta = (1.0 - cos_theta*cos_theta).sqrt();
 
-        let direction = if refraction_ratio * sin_theta > 1.0
-            || reflectance(cos_theta, refraction_ratio) > thread_rng().gen() {
-            unit_direction.reflect(rec.normal)
+        let cannot_refract = refraction_ratio * sin_theta > 1.0;
+
+        // A thin film makes the reflected fraction differ per channel, so
+        // choose by the average and reweight each channel
+        let (reflect, weight) = match self.thin_film {
+            Some(film) if !cannot_refract => {
+                let (outside_ir, inside_ir) = if rec.front_face { (1.0, ir) } else { (ir, 1.0) };
+                let r = film.reflectance_rgb(cos_theta, outside_ir, inside_ir, ray_in.wavelength);
+                let p = (r.x + r.y + r.z) / 3.0;
+                if p > thread_rng().gen() {
+                    (true, r / p)
+                } else {
+                    (false, (Color::one() - r) / (1.0 - p))
+                }
+            },
+            _ => (
+                cannot_refract || reflectance(cos_theta, refraction_ratio) > thread_rng().gen(),
+                Color::one()
+            ),
+        };
+
+        let scattered = if reflect {
+            ray_in.reflected(rec, unit_direction.reflect(rec.shading_normal))
+        } else {
+            ray_in.refracted(rec, unit_direction.refract(rec.shading_normal, refraction_ratio), refraction_ratio)
+        };
+
+        // Hitting the surface from the inside means the ray just travelled
+        // through the volume, so attenuate by the length of that path
+        let attenuation = if rec.front_face {
+            weight
         } else {
-            unit_direction.refract(rec.normal, refraction_ratio)
+            let absorption = self.absorption.color(&rec.tex_coord());
+            let distance = rec.t * ray_in.direction.length();
+            weight * Color::new(
+                (-absorption.x * distance).exp(),
+                (-absorption.y * distance).exp(),
+                (-absorption.z * distance).exp()
+            )
         };
 
         Some( Scatter {
-            scattered: Ray::new(rec.point, direction),
-            attenuation: Color::one()
+            scattered,
+            attenuation,
+            delta: true,
         })
     }
 }
 
-fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
+pub(crate) fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
     let r0 = (1.0-ref_idx) / (1.0+ref_idx);
     let r0 = r0*r0;
     r0 + (1.0-r0)*(1.0-cosine).powi(5)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tinted() {
+        let glass = Dielectric::tinted(1.5, Color::new(0.5, 1.0, 0.0), 2.0);
+        assert!((glass.absorption.x - 2.0_f64.ln() / 2.0).abs() < 1e-12);
+        assert_eq!(glass.absorption.y, 0.0);
+        // Opaque channels absorb strongly but stay finite
+        assert!(glass.absorption.z.is_finite() && glass.absorption.z > 6.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_tinted_zero_distance() {
+        Dielectric::tinted(1.5, Color::one(), 0.0);
+    }
+}
@@ -7,6 +7,17 @@ use crate::texture::Texture;
 #[derive(Clone)]
 pub struct DiffuseLight<T: Texture> {
     pub emit: T,
+    // Scales the emitted color, lets a normalized color be paired
+    // with the power of the fixture
+    pub intensity: f64,
+    // One sided lights only emit from the front (outward facing) side
+    pub two_sided: bool,
+}
+
+impl<T: Texture> DiffuseLight<T> {
+    pub fn new(emit: T) -> Self {
+        Self { emit, intensity: 1.0, two_sided: true }
+    }
 }
 
 impl<T: Texture> Material for DiffuseLight<T> {
@@ -14,7 +25,39 @@ impl<T: Texture> Material for DiffuseLight<T> {
         None
     }
 
-    fn emitted(&self, u: f64, v: f64) -> Color {
-        self.emit.color(u, v)
+    fn emitted(&self, _: &Ray, rec: &HitRecord) -> Color {
+        if !self.two_sided && !rec.front_face {
+            return Color::zero();
+        }
+        self.emit.color(&rec.tex_coord()) * self.intensity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::Plane;
+    use crate::vector::Vec3;
+
+    #[test]
+    fn test_emitted() {
+        let above = Vec3::new(0.0, 1.0, 0.0);
+        let mut light = DiffuseLight::new(Color::new(0.5, 0.25, 1.0));
+        light.intensity = 2.0;
+
+        // Two sided lights shine both ways, scaled by intensity
+        let ground = Plane::ground(light.clone());
+        for from in [above, -above] {
+            let (ray, rec) = ground.hit_from(from);
+            assert_eq!(rec.material.emitted(&ray, &rec), Color::new(1.0, 0.5, 2.0));
+        }
+
+        // One sided ones are dark from behind
+        light.two_sided = false;
+        let ground = Plane::ground(light);
+        let (ray, rec) = ground.hit_from(above);
+        assert_eq!(rec.material.emitted(&ray, &rec), Color::new(1.0, 0.5, 2.0));
+        let (ray, rec) = ground.hit_from(-above);
+        assert_eq!(rec.material.emitted(&ray, &rec), Color::zero());
     }
 }
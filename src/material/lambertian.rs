@@ -1,8 +1,9 @@
 use crate::material::{Scatter, Material};
-use crate::vector::Vec3;
+use crate::vector::{Vec3, Color};
 use crate::ray::Ray;
 use crate::shape::HitRecord;
 use crate::texture::Texture;
+use std::f64::consts::PI;
 
 #[derive(Clone)]
 pub struct Lambertian<T: Texture> {
@@ -11,17 +12,32 @@ pub struct Lambertian<T: Texture> {
 
 impl<T: Texture> Material for Lambertian<T> {
     fn scatter(&self, _: &Ray, rec: &HitRecord) -> Option<Scatter> {
-        let scatter_direction = rec.normal + Vec3::random_unit();
+        let scatter_direction = rec.shading_normal + Vec3::random_unit();
 
         let scatter_direction = if scatter_direction.near_zero() {
-            rec.normal
+            rec.shading_normal
         } else {
             scatter_direction
         };
 
+        // A tilted shading normal can point rays into the surface
+        if scatter_direction.dot(rec.normal) <= 0.0 {
+            return None;
+        }
+
         Some( Scatter {
             scattered: Ray::new(rec.point, scatter_direction),
-            attenuation: self.albedo.color(rec.u, rec.v),
+            attenuation: self.albedo.color(&rec.tex_coord()),
+            delta: false,
         })
     }
+
+    fn eval(&self, _: &Ray, rec: &HitRecord, direction: Vec3<f64>) -> Option<(Color, f64)> {
+        let cos = rec.shading_normal.dot(direction.normalized());
+        if cos <= 0.0 || rec.normal.dot(direction) <= 0.0 {
+            return Some((Color::zero(), 0.0));
+        }
+        let pdf = cos / PI;
+        Some((self.albedo.color(&rec.tex_coord()) * pdf, pdf))
+    }
 }
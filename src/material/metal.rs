@@ -2,25 +2,110 @@ use crate::material::{Scatter, Material};
 use crate::vector::{Vec3, Color};
 use crate::ray::Ray;
 use crate::shape::HitRecord;
+use crate::texture::Texture;
+use crate::material::ThinFilm;
+use std::f64::consts::PI;
 
 #[derive(Clone)]
-pub struct Metal {
-    pub albedo: Color,
-    pub fuzz: f64
+pub struct Metal<A: Texture = Color, F: Texture = f64> {
+    pub albedo: A,
+    pub fuzz: F,
+    // Optional coating giving iridescent reflections
+    pub thin_film: Option<ThinFilm>,
 }
 
-impl Material for Metal {
+impl<A: Texture, F: Texture> Metal<A, F> {
+    pub fn new(albedo: A, fuzz: F) -> Self {
+        Self { albedo, fuzz, thin_film: None }
+    }
+}
+
+impl<A: Texture, F: Texture> Metal<A, F> {
+    fn attenuation(&self, ray_in: &Ray, rec: &HitRecord) -> Color {
+        let albedo = self.albedo.color(&rec.tex_coord());
+        match self.thin_film {
+            Some(film) => {
+                let cos_theta = (-ray_in.direction.normalized()).dot(rec.shading_normal).min(1.0);
+                film.metal_reflectance_rgb(cos_theta, 1.0, albedo, ray_in.wavelength)
+            },
+            None => albedo,
+        }
+    }
+}
+
+impl<A: Texture, F: Texture> Material for Metal<A, F> {
     fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> Option<Scatter> {
-        let reflected = ray_in.direction.normalized().reflect(rec.normal);
-        let scattered = Ray::new(rec.point, reflected + Vec3::random_in_unit_sphere()*self.fuzz);
+        let fuzz = self.fuzz.value(&rec.tex_coord());
+        let unit_direction = ray_in.direction.normalized();
+        let reflected = unit_direction.reflect(rec.shading_normal);
+        let scattered = ray_in.reflected(rec, reflected + Vec3::random_in_unit_sphere()*fuzz);
 
         if scattered.direction.dot(rec.normal) > 0.0 {
-            return Some( Scatter {
+            Some( Scatter {
                 scattered,
-                attenuation: self.albedo
-            });
+                attenuation: self.attenuation(ray_in, rec),
+                delta: fuzz <= 0.0,
+            })
         } else {
+            None
+        }
+    }
+
+    // Only fuzzy reflections spread light over more than one direction
+    fn eval(&self, ray_in: &Ray, rec: &HitRecord, direction: Vec3<f64>) -> Option<(Color, f64)> {
+        let fuzz = self.fuzz.value(&rec.tex_coord());
+        if fuzz <= 0.0 {
             return None;
         }
+        if direction.dot(rec.normal) <= 0.0 {
+            return Some((Color::zero(), 0.0));
+        }
+
+        let reflected = ray_in.direction.normalized().reflect(rec.shading_normal);
+        let pdf = fuzzy_reflection_pdf(reflected, fuzz, direction.normalized());
+        Some((self.attenuation(ray_in, rec) * pdf, pdf))
+    }
+}
+
+// Density of the direction of reflected + fuzz * (a random point in the
+// unit ball), for a unit reflected direction. The point is uniform over
+// a ball of radius fuzz around reflected, so this is the part of the
+// ball's volume along the direction, weighted by distance squared.
+pub(crate) fn fuzzy_reflection_pdf(reflected: Vec3<f64>, fuzz: f64, direction: Vec3<f64>) -> f64 {
+    let c = direction.dot(reflected);
+    let discriminant = c*c - 1.0 + fuzz*fuzz;
+    if discriminant < 0.0 {
+        return 0.0;
+    }
+    let far = c + discriminant.sqrt();
+    let near = (c - discriminant.sqrt()).max(0.0);
+    if far <= 0.0 {
+        return 0.0;
+    }
+    (far.powi(3) - near.powi(3)) / (4.0 * PI * fuzz.powi(3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_reflection_pdf() {
+        // Integrates to 1 over the sphere, for fuzz below and above 1
+        let reflected = Vec3::new(0.6, 0.8, 0.0);
+        for fuzz in [0.3, 1.0, 1.7] {
+            let (nu, nv) = (400, 200);
+            let mut total = 0.0;
+            for j in 0..nv {
+                let theta = PI * (j as f64 + 0.5) / nv as f64;
+                for i in 0..nu {
+                    let phi = 2.0 * PI * (i as f64 + 0.5) / nu as f64;
+                    let d = Vec3::new(phi.cos()*theta.sin(), theta.cos(), phi.sin()*theta.sin());
+                    total += fuzzy_reflection_pdf(reflected, fuzz, d) * theta.sin()
+                        * (PI / nv as f64) * (2.0 * PI / nu as f64);
+                }
+            }
+            assert!((total - 1.0).abs() < 1e-2, "{total} for fuzz {fuzz}");
+        }
     }
 }
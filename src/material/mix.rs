@@ -0,0 +1,108 @@
+use crate::material::{Scatter, Material, add_eval};
+use crate::vector::{Vec3, Color};
+use crate::ray::Ray;
+use crate::shape::HitRecord;
+use crate::texture::Texture;
+use rand::{thread_rng, Rng};
+
+// Blends two materials, weight 0 is entirely `first` and weight 1 is
+// entirely `second`. Each scatter picks one of them stochastically.
+#[derive(Clone)]
+pub struct Mix<A: Material, B: Material, W: Texture = f64> {
+    pub first: A,
+    pub second: B,
+    pub weight: W,
+}
+
+impl<A: Material, B: Material, W: Texture> Mix<A, B, W> {
+    // Textures outside [0, 1] would give negative weights
+    fn weight(&self, rec: &HitRecord) -> f64 {
+        self.weight.value(&rec.tex_coord()).clamp(0.0, 1.0)
+    }
+}
+
+impl<A: Material, B: Material, W: Texture> Material for Mix<A, B, W> {
+    fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> Option<Scatter> {
+        let weight = self.weight(rec);
+        if thread_rng().gen::<f64>() < weight {
+            self.second.scatter(ray_in, rec)
+        } else {
+            self.first.scatter(ray_in, rec)
+        }
+    }
+
+    fn emitted(&self, ray_in: &Ray, rec: &HitRecord) -> Color {
+        let weight = self.weight(rec);
+        self.first.emitted(ray_in, rec) * (1.0 - weight) + self.second.emitted(ray_in, rec) * weight
+    }
+
+    fn opacity(&self, rec: &HitRecord) -> f64 {
+        let weight = self.weight(rec);
+        self.first.opacity(rec) * (1.0 - weight) + self.second.opacity(rec) * weight
+    }
+
+    // Delta lobes of either material are left out, as scatter marks them
+    fn eval(&self, ray_in: &Ray, rec: &HitRecord, direction: Vec3<f64>) -> Option<(Color, f64)> {
+        let weight = self.weight(rec);
+        let first = self.first.eval(ray_in, rec, direction)
+            .map(|(f, pdf)| (f * (1.0 - weight), pdf * (1.0 - weight)));
+        let second = self.second.eval(ray_in, rec, direction)
+            .map(|(f, pdf)| (f * weight, pdf * weight));
+        add_eval(first, second)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::{Lambertian, OrenNayar, DiffuseLight};
+    use crate::shape::Plane;
+
+    #[test]
+    fn test_endpoints() {
+        let red = Lambertian { albedo: Color::new(0.8, 0.1, 0.1) };
+        let blue = OrenNayar { albedo: Color::new(0.1, 0.1, 0.8), sigma: 0.5 };
+        let direction = Vec3::new(0.3, 1.0, 0.2);
+
+        // Weights past either end are clamped to it
+        for (weight, expected) in [(0.0, red.albedo), (1.0, blue.albedo), (-0.5, red.albedo), (1.5, blue.albedo)] {
+            let ground = Plane::ground(Mix {
+                first: red.clone(),
+                second: blue.clone(),
+                weight,
+            });
+            let (ray, rec) = ground.hit_from(Vec3::new(-1.0, 1.0, 0.0));
+
+            // Weight 0 and 1 behave exactly like the endpoint materials
+            let (f, pdf) = rec.material.eval(&ray, &rec, direction).unwrap();
+            let (f_end, pdf_end) = if weight < 0.5 {
+                red.eval(&ray, &rec, direction).unwrap()
+            } else {
+                blue.eval(&ray, &rec, direction).unwrap()
+            };
+            assert!((f - f_end).length() < 1e-12);
+            assert!((pdf - pdf_end).abs() < 1e-12);
+
+            // Only the chosen material is ever scattered off, blue's
+            // attenuation is its albedo times a positive factor
+            for _ in 0..100 {
+                let a = rec.material.scatter(&ray, &rec).unwrap().attenuation;
+                let ratio = a / expected;
+                assert!((ratio.x - ratio.z).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_weight_clamped() {
+        // Out of range weights never make the emission negative
+        let ground = Plane::ground(Mix {
+            first: DiffuseLight::new(Color::one()),
+            second: Lambertian { albedo: Color::one() },
+            weight: 1.5,
+        });
+        let (ray, rec) = ground.hit_from(Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(rec.material.emitted(&ray, &rec), Color::zero());
+        assert_eq!(rec.material.opacity(&rec), 1.0);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::material::{Scatter, Material};
+use crate::vector::{Vec3, Color};
+use crate::ray::Ray;
+use crate::shape::HitRecord;
+use crate::texture::Texture;
+
+// Perturbs the shading normal of a material with a tangent space normal
+// map (RGB in [0, 1] mapping to XYZ in [-1, 1], blue pointing out of the
+// surface). The hit point is left on the actual geometry. Image maps
+// should be loaded with ColorSpace::Linear.
+#[derive(Clone)]
+pub struct NormalMap<M: Material, T: Texture> {
+    pub material: M,
+    pub map: T,
+    // Scales the tangential part of the mapped normals
+    pub strength: f64,
+}
+
+impl<M: Material, T: Texture> NormalMap<M, T> {
+    fn shade<'a>(&self, rec: &HitRecord<'a>) -> HitRecord<'a> {
+        let n = rec.shading_normal;
+        let t = (rec.dpdu - n*n.dot(rec.dpdu)).normalized();
+        let b = n.cross(t);
+        // Keep the bitangent pointing along v, the normal may be flipped
+        let b = if b.dot(rec.dpdv) < 0.0 { -b } else { b };
+
+        let c = self.map.color(&rec.tex_coord()) * 2.0 - 1.0;
+        let mapped = t*(c.x*self.strength) + b*(c.y*self.strength) + n*c.z;
+
+        // The geometric normal stays put for telling the sides apart
+        let mut shaded = *rec;
+        if !mapped.near_zero() {
+            let mapped = mapped.normalized();
+            shaded.shading_normal = if mapped.dot(rec.normal) < 0.0 { -mapped } else { mapped };
+        }
+        shaded
+    }
+}
+
+impl<M: Material, T: Texture> Material for NormalMap<M, T> {
+    fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> Option<Scatter> {
+        self.material.scatter(ray_in, &self.shade(rec))
+    }
+
+    fn emitted(&self, ray_in: &Ray, rec: &HitRecord) -> Color {
+        self.material.emitted(ray_in, &self.shade(rec))
+    }
+
+    fn opacity(&self, rec: &HitRecord) -> f64 {
+        self.material.opacity(rec)
+    }
+
+    fn eval(&self, ray_in: &Ray, rec: &HitRecord, direction: Vec3<f64>) -> Option<(Color, f64)> {
+        self.material.eval(ray_in, &self.shade(rec), direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::shape::Plane;
+
+    #[test]
+    fn test_geometric_normal_kept() {
+        // A map tilting the normal far over towards +u
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let ground = Plane::ground(NormalMap {
+            material: Lambertian { albedo: Color::one() },
+            map: Color::new(0.95, 0.5, 0.6),
+            strength: 1.0,
+        });
+        let (ray, rec) = ground.hit_from(up);
+        let shaded = ground.material.shade(&rec);
+        assert_eq!(shaded.normal, up);
+        assert!(shaded.shading_normal.dot(up) < 0.5);
+        assert!((shaded.shading_normal.dot(rec.dpdu.normalized()).abs() - 0.9 / 0.85f64.sqrt()).abs() < 1e-9);
+
+        // Light never leaks through from below the actual surface
+        for _ in 0..1000 {
+            if let Some(scatter) = rec.material.scatter(&ray, &rec) {
+                assert!(scatter.scattered.direction.dot(up) > 0.0);
+            }
+        }
+        let below = shaded.shading_normal - up * 0.5;
+        assert!(below.dot(shaded.shading_normal) > 0.0 && below.dot(up) < 0.0);
+        let (f, pdf) = rec.material.eval(&ray, &rec, below).unwrap();
+        assert!(f.near_zero() && pdf == 0.0);
+    }
+}
@@ -0,0 +1,123 @@
+use crate::material::{Scatter, Material};
+use crate::vector::{Vec3, Color};
+use crate::ray::Ray;
+use crate::shape::HitRecord;
+use crate::texture::Texture;
+use std::f64::consts::PI;
+
+// Rough diffuse surface (clay, concrete, fabric). sigma is the standard
+// deviation of the microfacet angle in radians, 0 matches Lambertian.
+#[derive(Clone)]
+pub struct OrenNayar<T: Texture, S: Texture = f64> {
+    pub albedo: T,
+    pub sigma: S,
+}
+
+impl<T: Texture, S: Texture> OrenNayar<T, S> {
+    // Oren-Nayar factor multiplying the Lambertian albedo
+    fn factor(&self, rec: &HitRecord, wo: Vec3<f64>, wi: Vec3<f64>) -> f64 {
+        let n = rec.shading_normal;
+        let cos_i = n.dot(wi).clamp(0.0, 1.0);
+        let cos_o = n.dot(wo).clamp(0.0, 1.0);
+        let sin_i = (1.0 - cos_i*cos_i).sqrt();
+        let sin_o = (1.0 - cos_o*cos_o).sqrt();
+
+        // Cosine of the azimuthal angle between the two directions
+        let max_cos = if sin_i > 1e-4 && sin_o > 1e-4 {
+            let ti = (wi - n*cos_i) / sin_i;
+            let to = (wo - n*cos_o) / sin_o;
+            ti.dot(to).max(0.0)
+        } else {
+            0.0
+        };
+
+        let (sin_alpha, tan_beta) = if cos_i > cos_o {
+            (sin_o, sin_i / cos_i)
+        } else {
+            (sin_i, sin_o / cos_o.max(1e-8))
+        };
+
+        let sigma = self.sigma.value(&rec.tex_coord());
+        let sigma2 = sigma * sigma;
+        let a = 1.0 - sigma2 / (2.0 * (sigma2 + 0.33));
+        let b = 0.45 * sigma2 / (sigma2 + 0.09);
+        a + b * max_cos * sin_alpha * tan_beta
+    }
+}
+
+impl<T: Texture, S: Texture> Material for OrenNayar<T, S> {
+    fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> Option<Scatter> {
+        let scatter_direction = rec.shading_normal + Vec3::random_unit();
+
+        let scatter_direction = if scatter_direction.near_zero() {
+            rec.shading_normal
+        } else {
+            scatter_direction
+        };
+
+        // A tilted shading normal can point rays into the surface
+        if scatter_direction.dot(rec.normal) <= 0.0 {
+            return None;
+        }
+
+        let wi = scatter_direction.normalized();
+        let wo = -ray_in.direction.normalized();
+
+        // Cosine weighted sampling cancels the cosine and 1/pi terms,
+        // leaving albedo scaled by the Oren-Nayar factor
+        Some( Scatter {
+            scattered: Ray::new(rec.point, scatter_direction),
+            attenuation: self.albedo.color(&rec.tex_coord()) * self.factor(rec, wo, wi),
+            delta: false,
+        })
+    }
+
+    fn eval(&self, ray_in: &Ray, rec: &HitRecord, direction: Vec3<f64>) -> Option<(Color, f64)> {
+        let wi = direction.normalized();
+        let cos = rec.shading_normal.dot(wi);
+        if cos <= 0.0 || rec.normal.dot(wi) <= 0.0 {
+            return Some((Color::zero(), 0.0));
+        }
+        let wo = -ray_in.direction.normalized();
+        let pdf = cos / PI;
+        Some((self.albedo.color(&rec.tex_coord()) * (self.factor(rec, wo, wi) * pdf), pdf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::shape::Plane;
+
+    #[test]
+    fn test_smooth_is_lambertian() {
+        let albedo = Color::new(0.7, 0.5, 0.3);
+        let smooth = OrenNayar { albedo, sigma: 0.0 };
+        let lambertian = Lambertian { albedo };
+        let ground = Plane::ground(smooth);
+        let (ray, rec) = ground.hit_from(Vec3::new(-1.0, 0.5, 0.3));
+
+        for direction in [Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.8, 0.3, -0.2), Vec3::new(0.5, -0.1, 0.0)] {
+            let (f, pdf) = rec.material.eval(&ray, &rec, direction).unwrap();
+            let (f_l, pdf_l) = lambertian.eval(&ray, &rec, direction).unwrap();
+            assert!((f - f_l).length() < 1e-12);
+            assert!((pdf - pdf_l).abs() < 1e-12);
+        }
+        for _ in 0..100 {
+            let scatter = rec.material.scatter(&ray, &rec).unwrap();
+            assert!((scatter.attenuation - albedo).length() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_rough_retroreflects() {
+        // Rough surfaces look brighter back towards the light than off to
+        // the side
+        let ground = Plane::ground(OrenNayar { albedo: Color::one(), sigma: 0.5 });
+        let (ray, rec) = ground.hit_from(Vec3::new(-1.0, 1.0, 0.0));
+        let (back, _) = rec.material.eval(&ray, &rec, Vec3::new(-1.0, 1.0, 0.0)).unwrap();
+        let (side, _) = rec.material.eval(&ray, &rec, Vec3::new(0.0, 1.0, 1.0)).unwrap();
+        assert!(back.x > side.x);
+    }
+}
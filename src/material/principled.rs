@@ -0,0 +1,437 @@
+use crate::material::{Scatter, Material};
+use crate::vector::{Vec3, Color};
+use crate::ray::Ray;
+use crate::shape::HitRecord;
+use crate::texture::{Texture, TexCoord};
+use crate::spectrum;
+use rand::{thread_rng, Rng};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+// Disney style "uber" material. Every parameter is driven by a texture,
+// plain f64 and Color values can be used where a parameter is constant.
+// Lobes are picked stochastically: clearcoat, then metal, then the
+// dielectric base (specular, transmission or diffuse + sheen). The odds
+// only depend on the view direction, so eval can add the lobes up.
+#[derive(Clone)]
+pub struct Principled {
+    pub base_color: Arc<dyn Texture>,
+    pub metallic: Arc<dyn Texture>,
+    pub roughness: Arc<dyn Texture>,
+    pub specular: Arc<dyn Texture>,
+    pub sheen: Arc<dyn Texture>,
+    pub sheen_tint: Arc<dyn Texture>,
+    pub clearcoat: Arc<dyn Texture>,
+    pub clearcoat_roughness: Arc<dyn Texture>,
+    pub transmission: Arc<dyn Texture>,
+    pub subsurface: Arc<dyn Texture>,
+    pub ir: f64,
+}
+
+// Probability of picking each lobe, adding up to 1
+struct Lobes {
+    clearcoat: f64,
+    metallic: f64,
+    specular: f64,
+    transmission: f64,
+    diffuse: f64,
+}
+
+impl Principled {
+    // Rough, non metallic dielectric with the given base color
+    pub fn new(base_color: impl Texture + 'static) -> Self {
+        Self {
+            base_color: Arc::new(base_color),
+            metallic: Arc::new(0.0),
+            roughness: Arc::new(0.5),
+            specular: Arc::new(0.5),
+            sheen: Arc::new(0.0),
+            sheen_tint: Arc::new(0.5),
+            clearcoat: Arc::new(0.0),
+            clearcoat_roughness: Arc::new(0.03),
+            transmission: Arc::new(0.0),
+            subsurface: Arc::new(0.0),
+            ir: 1.5,
+        }
+    }
+
+    // Fresnel reflectance of the coat and specular layers is taken at the
+    // shading normal
+    fn lobes(&self, coord: &TexCoord, cos_v: f64) -> Lobes {
+        let clearcoat = self.clearcoat.value(coord).clamp(0.0, 1.0) * schlick(0.04, cos_v);
+        let metallic = (1.0 - clearcoat) * self.metallic.value(coord).clamp(0.0, 1.0);
+        let dielectric = 1.0 - clearcoat - metallic;
+        let f0 = 0.08 * self.specular.value(coord).clamp(0.0, 1.0);
+        let specular = dielectric * schlick(f0, cos_v);
+        let transmission = (dielectric - specular) * self.transmission.value(coord).clamp(0.0, 1.0);
+        Lobes {
+            clearcoat,
+            metallic,
+            specular,
+            transmission,
+            diffuse: dielectric - specular - transmission,
+        }
+    }
+
+    // Diffuse with Disney retro-reflection, subsurface approximation and
+    // sheen, as the attenuation of a cosine weighted sample (BRDF times pi)
+    fn diffuse(&self, coord: &TexCoord, base_color: Color, n: Vec3<f64>, wo: Vec3<f64>, wi: Vec3<f64>) -> Color {
+        let cos_l = n.dot(wi).max(1e-6);
+        let cos_v = n.dot(wo).max(1e-6);
+        let cos_d = wi.dot((wi + wo).normalized());
+        let roughness = self.roughness.value(coord).clamp(0.0, 1.0);
+        let fl = schlick_weight(cos_l);
+        let fv = schlick_weight(cos_v);
+
+        let fd90 = 0.5 + 2.0 * cos_d * cos_d * roughness;
+        let fd = (1.0 + (fd90 - 1.0) * fl) * (1.0 + (fd90 - 1.0) * fv);
+
+        let fss90 = cos_d * cos_d * roughness;
+        let fss = (1.0 + (fss90 - 1.0) * fl) * (1.0 + (fss90 - 1.0) * fv);
+        let ss = 1.25 * (fss * (1.0 / (cos_l + cos_v) - 0.5) + 0.5);
+
+        let subsurface = self.subsurface.value(coord).clamp(0.0, 1.0);
+        let diffuse = fd + (ss - fd) * subsurface;
+
+        let luminance = spectrum::luminance(base_color);
+        let tint = if luminance > 0.0 { base_color / luminance } else { Color::one() };
+        let sheen_tint = self.sheen_tint.value(coord);
+        let sheen_color = Color::one() + (tint - Color::one()) * sheen_tint;
+        let sheen = sheen_color * self.sheen.value(coord) * schlick_weight(cos_d);
+
+        base_color * diffuse + sheen * PI
+    }
+}
+
+impl Material for Principled {
+    fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> Option<Scatter> {
+        let mut rng = thread_rng();
+        let coord = rec.tex_coord();
+        let wo = -ray_in.direction.normalized();
+        let n = rec.shading_normal;
+        let alpha = roughness_to_alpha(self.roughness.value(&coord));
+        let transmission = self.transmission.value(&coord).clamp(0.0, 1.0);
+
+        // Transmitted rays reach the back face from inside, so just handle
+        // the refractive interface on the way out. Opaque surfaces seen
+        // from behind (planes, open meshes) are shaded like the front, the
+        // normal already faces the ray.
+        if !rec.front_face && transmission > 0.0 {
+            let h = sample_ggx(n, alpha);
+            let direction = refract_or_reflect(wo, h, self.ir, &mut rng);
+            return Some( Scatter {
+                scattered: Ray::new(rec.point, direction),
+                attenuation: Color::one(),
+                delta: false,
+            });
+        }
+
+        let base_color = self.base_color.color(&coord);
+        let lobes = self.lobes(&coord, n.dot(wo));
+        let mut r = rng.gen::<f64>();
+
+        if r < lobes.clearcoat {
+            let cc_alpha = roughness_to_alpha(self.clearcoat_roughness.value(&coord));
+            return reflect_scatter(rec, wo, sample_ggx(n, cc_alpha), cc_alpha, Color::one());
+        }
+        r -= lobes.clearcoat;
+
+        if r < lobes.metallic {
+            let h = sample_ggx(n, alpha);
+            return reflect_scatter(rec, wo, h, alpha, metal_fresnel(base_color, wo.dot(h)));
+        }
+        r -= lobes.metallic;
+
+        if r < lobes.specular {
+            return reflect_scatter(rec, wo, sample_ggx(n, alpha), alpha, Color::one());
+        }
+        r -= lobes.specular;
+
+        // Lobes.specular already took the Fresnel reflection, and entering
+        // the denser side can't reflect internally, so always refract
+        if r < lobes.transmission {
+            let direction = (-wo).refract(sample_ggx(n, alpha), 1.0/self.ir);
+            if direction.dot(rec.normal) >= 0.0 {
+                return None;
+            }
+            return Some( Scatter {
+                scattered: Ray::new(rec.point, direction),
+                attenuation: base_color,
+                delta: false,
+            });
+        }
+
+        // Cosine weighted like Lambertian
+        let scatter_direction = n + Vec3::random_unit();
+        let wi = if scatter_direction.near_zero() {
+            n
+        } else {
+            scatter_direction.normalized()
+        };
+        if wi.dot(rec.normal) <= 0.0 {
+            return None;
+        }
+
+        Some( Scatter {
+            scattered: Ray::new(rec.point, wi),
+            attenuation: self.diffuse(&coord, base_color, n, wo, wi),
+            delta: false,
+        })
+    }
+
+    fn eval(&self, ray_in: &Ray, rec: &HitRecord, direction: Vec3<f64>) -> Option<(Color, f64)> {
+        let coord = rec.tex_coord();
+        let wo = -ray_in.direction.normalized();
+        let wi = direction.normalized();
+        let n = rec.shading_normal;
+        let alpha = roughness_to_alpha(self.roughness.value(&coord));
+        let transmission = self.transmission.value(&coord).clamp(0.0, 1.0);
+
+        if !rec.front_face && transmission > 0.0 {
+            let pdf = transmission_pdf(n, wo, wi, alpha, self.ir);
+            return Some((Color::one() * pdf, pdf));
+        }
+
+        let base_color = self.base_color.color(&coord);
+        let lobes = self.lobes(&coord, n.dot(wo));
+        let mut f = Color::zero();
+        let mut pdf = 0.0;
+
+        if wi.dot(rec.normal) > 0.0 && n.dot(wi) > 0.0 && n.dot(wo) > 0.0 {
+            if lobes.clearcoat > 0.0 {
+                let cc_alpha = roughness_to_alpha(self.clearcoat_roughness.value(&coord));
+                let (value, p) = reflect_eval(n, wo, wi, cc_alpha);
+                f += Color::one() * (value * lobes.clearcoat);
+                pdf += p * lobes.clearcoat;
+            }
+
+            let (value, p) = reflect_eval(n, wo, wi, alpha);
+            let h = (wo + wi).normalized();
+            let tint = metal_fresnel(base_color, wo.dot(h)) * lobes.metallic + Color::one() * lobes.specular;
+            f += tint * value;
+            pdf += p * (lobes.metallic + lobes.specular);
+
+            let cosine_pdf = n.dot(wi) / PI;
+            f += self.diffuse(&coord, base_color, n, wo, wi) * (cosine_pdf * lobes.diffuse);
+            pdf += cosine_pdf * lobes.diffuse;
+        }
+
+        if lobes.transmission > 0.0 {
+            let p = refraction_pdf(n, wo, wi, alpha, 1.0/self.ir) * lobes.transmission;
+            f += base_color * p;
+            pdf += p;
+        }
+
+        Some((f, pdf))
+    }
+}
+
+fn roughness_to_alpha(roughness: f64) -> f64 {
+    let r = roughness.clamp(0.0, 1.0);
+    (r * r).max(1e-3)
+}
+
+fn schlick_weight(cosine: f64) -> f64 {
+    (1.0 - cosine).clamp(0.0, 1.0).powi(5)
+}
+
+fn schlick(f0: f64, cosine: f64) -> f64 {
+    f0 + (1.0 - f0) * schlick_weight(cosine)
+}
+
+fn metal_fresnel(base_color: Color, cosine: f64) -> Color {
+    base_color + (Color::one() - base_color) * schlick_weight(cosine)
+}
+
+// GGX distribution of microfacet normals
+fn ggx_d(n: Vec3<f64>, h: Vec3<f64>, alpha: f64) -> f64 {
+    let cos = n.dot(h);
+    if cos <= 0.0 {
+        return 0.0;
+    }
+    let a2 = alpha * alpha;
+    let t = cos * cos * (a2 - 1.0) + 1.0;
+    a2 / (PI * t * t)
+}
+
+// Samples a GGX microfacet normal around n, proportional to D(h)(n.h)
+fn sample_ggx(n: Vec3<f64>, alpha: f64) -> Vec3<f64> {
+    let mut rng = thread_rng();
+    let r1: f64 = rng.gen();
+    let r2: f64 = rng.gen();
+
+    let phi = 2.0 * PI * r1;
+    let cos_theta = ((1.0 - r2) / (1.0 + (alpha*alpha - 1.0) * r2)).sqrt();
+    let sin_theta = (1.0 - cos_theta*cos_theta).max(0.0).sqrt();
+
+    let a = if n.x.abs() > 0.9 { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) };
+    let t = n.cross(a).normalized();
+    let b = n.cross(t);
+
+    (t * (sin_theta * phi.cos()) + b * (sin_theta * phi.sin()) + n * cos_theta).normalized()
+}
+
+fn smith_g1(cosine: f64, alpha: f64) -> f64 {
+    let a2 = alpha * alpha;
+    2.0 * cosine / (cosine + (a2 + (1.0 - a2) * cosine * cosine).sqrt())
+}
+
+// Masking-shadowing term left over from sampling D(h)(n.h), capped at 1
+fn reflect_weight(n: Vec3<f64>, wo: Vec3<f64>, wi: Vec3<f64>, h: Vec3<f64>, alpha: f64) -> f64 {
+    let weight = smith_g1(n.dot(wi), alpha) * smith_g1(n.dot(wo), alpha) * wo.dot(h).abs()
+        / (n.dot(wo) * n.dot(h)).max(1e-8);
+    weight.min(1.0)
+}
+
+// Reflects wo about the sampled microfacet normal h, weighting by the
+// masking-shadowing term
+fn reflect_scatter(rec: &HitRecord, wo: Vec3<f64>, h: Vec3<f64>, alpha: f64, tint: Color) -> Option<Scatter> {
+    let n = rec.shading_normal;
+    let wi = (-wo).reflect(h);
+    let cos_l = n.dot(wi);
+    let cos_v = n.dot(wo);
+    if cos_l <= 0.0 || cos_v <= 0.0 || wi.dot(rec.normal) <= 0.0 {
+        return None;
+    }
+
+    Some( Scatter {
+        scattered: Ray::new(rec.point, wi),
+        attenuation: tint * reflect_weight(n, wo, wi, h, alpha),
+        delta: false,
+    })
+}
+
+// Untinted BRDF times cosine of the reflect_scatter lobe, and its pdf,
+// for wo and wi above the surface
+fn reflect_eval(n: Vec3<f64>, wo: Vec3<f64>, wi: Vec3<f64>, alpha: f64) -> (f64, f64) {
+    let h = (wo + wi).normalized();
+    let pdf = ggx_d(n, h, alpha) * n.dot(h) / (4.0 * wo.dot(h).abs()).max(1e-8);
+    (pdf * reflect_weight(n, wo, wi, h, alpha), pdf)
+}
+
+fn transmission_fresnel(cos_theta: f64, refraction_ratio: f64) -> f64 {
+    let sin_theta = (1.0 - cos_theta*cos_theta).max(0.0).sqrt();
+    if refraction_ratio * sin_theta > 1.0 {
+        return 1.0;
+    }
+    let r0 = ((1.0 - refraction_ratio) / (1.0 + refraction_ratio)).powi(2);
+    schlick(r0, cos_theta)
+}
+
+// Refracts wo through the microfacet normal h, falling back to
+// reflection by Fresnel or total internal reflection
+fn refract_or_reflect(wo: Vec3<f64>, h: Vec3<f64>, refraction_ratio: f64, rng: &mut impl Rng) -> Vec3<f64> {
+    let cos_theta = wo.dot(h).min(1.0);
+    if transmission_fresnel(cos_theta, refraction_ratio) > rng.gen() {
+        (-wo).reflect(h)
+    } else {
+        (-wo).refract(h, refraction_ratio)
+    }
+}
+
+// The microfacet normal refracting wo into wi
+fn refraction_normal(n: Vec3<f64>, wo: Vec3<f64>, wi: Vec3<f64>, refraction_ratio: f64) -> Vec3<f64> {
+    let h = (wo * refraction_ratio + wi).normalized();
+    if h.dot(n) < 0.0 { -h } else { h }
+}
+
+// Density of refracting wo into wi through h from sample_ggx, with the
+// change of variables from h to wi
+fn refraction_pdf(n: Vec3<f64>, wo: Vec3<f64>, wi: Vec3<f64>, alpha: f64, refraction_ratio: f64) -> f64 {
+    if n.dot(wi) * n.dot(wo) >= 0.0 {
+        return 0.0;
+    }
+    let h = refraction_normal(n, wo, wi, refraction_ratio);
+    let (cos_o, cos_i) = (wo.dot(h), wi.dot(h));
+    let denominator = refraction_ratio * cos_o + cos_i;
+    if cos_o <= 0.0 || denominator == 0.0 {
+        return 0.0;
+    }
+    ggx_d(n, h, alpha) * n.dot(h) * cos_i.abs() / (denominator * denominator)
+}
+
+// Density of refract_or_reflect's direction with h from sample_ggx
+fn transmission_pdf(n: Vec3<f64>, wo: Vec3<f64>, wi: Vec3<f64>, alpha: f64, refraction_ratio: f64) -> f64 {
+    if n.dot(wi) * n.dot(wo) > 0.0 {
+        let h = (wo + wi).normalized();
+        let cos = wo.dot(h);
+        return transmission_fresnel(cos.min(1.0), refraction_ratio) * ggx_d(n, h, alpha) * n.dot(h)
+            / (4.0 * cos.abs()).max(1e-8);
+    }
+
+    let cos_o = wo.dot(refraction_normal(n, wo, wi, refraction_ratio)).clamp(0.0, 1.0);
+    (1.0 - transmission_fresnel(cos_o, refraction_ratio)) * refraction_pdf(n, wo, wi, alpha, refraction_ratio)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::Plane;
+
+    // Average attenuation, counting absorbed rays as black, and the fraction
+    // of scattered rays leaving on the viewer's side
+    fn scatter_stats(material: Principled, from_below: bool) -> (Color, f64) {
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let ground = Plane::ground(material);
+        let side = if from_below { -1.0 } else { 1.0 };
+        // Steep enough to get out of glass from inside
+        let (ray, rec) = ground.hit_from(Vec3::new(-0.4, side, 0.0));
+
+        let n = 20000;
+        let mut sum = Color::zero();
+        let mut scattered = 0;
+        let mut reflected = 0;
+        for _ in 0..n {
+            if let Some(scatter) = rec.material.scatter(&ray, &rec) {
+                sum += scatter.attenuation;
+                scattered += 1;
+                if scatter.scattered.direction.dot(up) * side > 0.0 {
+                    reflected += 1;
+                }
+            }
+        }
+        (sum / n as f64, reflected as f64 / scattered as f64)
+    }
+
+    #[test]
+    fn test_energy() {
+        // A white surface can't reflect more than arrives, and only loses
+        // a little to rays scattered below the surface
+        for (metallic, roughness) in [(0.0, 0.5), (1.0, 0.2), (0.0, 1.0)] {
+            let mut material = Principled::new(Color::one());
+            material.metallic = Arc::new(metallic);
+            material.roughness = Arc::new(roughness);
+            let (albedo, reflected) = scatter_stats(material, false);
+            for c in [albedo.x, albedo.y, albedo.z] {
+                assert!(c > 0.7 && c < 1.05, "albedo {c} for metallic {metallic}");
+            }
+            assert_eq!(reflected, 1.0);
+        }
+    }
+
+    #[test]
+    fn test_opaque_back_face() {
+        // Seen from behind an opaque surface still reflects
+        let (albedo, reflected) = scatter_stats(Principled::new(Color::new(0.5, 0.5, 0.5)), true);
+        assert_eq!(reflected, 1.0);
+        assert!(albedo.x > 0.3 && albedo.x < 0.6);
+
+        // While a transmissive one lets light out through the back
+        let mut glass = Principled::new(Color::one());
+        glass.transmission = Arc::new(1.0);
+        let (_, reflected) = scatter_stats(glass, true);
+        assert!(reflected < 0.5);
+    }
+
+    #[test]
+    fn test_transmission_fresnel() {
+        // Entering glass only the specular lobe reflects, around F = 0.04
+        // near normal incidence rather than F + (1 - F)F
+        let mut glass = Principled::new(Color::one());
+        glass.transmission = Arc::new(1.0);
+        glass.roughness = Arc::new(0.2);
+        let (albedo, reflected) = scatter_stats(glass, false);
+        assert!(reflected > 0.03 && reflected < 0.05, "reflected {reflected}");
+        assert!(albedo.x > 0.95);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::material::{Scatter, Material};
+use crate::material::dielectric::reflectance;
+use crate::vector::{Vec3, Color};
+use crate::ray::Ray;
+use crate::shape::HitRecord;
+use crate::texture::Texture;
+use rand::{thread_rng, Rng};
+use std::f64::consts::PI;
+
+// Translucent material (skin, wax, marble, milk) simulated with a random
+// walk through the volume enclosed by the shape. Light refracts in at the
+// boundary, scatters isotropically every mean free path on average and
+// is absorbed according to the scattering albedo. On the way out the
+// boundary is treated as rough, spreading light diffusely, so lights can
+// be sampled directly where the walk leaves the medium.
+//
+// Dense media need many bounces, so raise max_depth for low mean free
+// paths or the result will come out too dark.
+#[derive(Clone)]
+pub struct Subsurface<A: Texture = Color> {
+    pub albedo: A,
+    // Average distance between scattering events per color channel
+    pub mean_free_path: Color,
+    pub ir: f64,
+}
+
+impl<A: Texture> Subsurface<A> {
+    fn sigma_t(&self) -> Color {
+        Color::new(
+            1.0 / self.mean_free_path.x.max(1e-8),
+            1.0 / self.mean_free_path.y.max(1e-8),
+            1.0 / self.mean_free_path.z.max(1e-8)
+        )
+    }
+
+    // Fraction of the light reaching the boundary from inside that gets
+    // out, rather than reflecting back in
+    fn exit_transmittance(&self, ray_in: &Ray, rec: &HitRecord) -> f64 {
+        let cos_theta = ray_in.direction.normalized().dot(-rec.shading_normal).min(1.0);
+        let sin_theta = (1.0 - cos_theta*cos_theta).max(0.0).sqrt();
+        if self.ir * sin_theta > 1.0 {
+            0.0
+        } else {
+            1.0 - reflectance(cos_theta, self.ir)
+        }
+    }
+}
+
+impl<A: Texture> Material for Subsurface<A> {
+    fn scatter(&self, ray_in: &Ray, rec: &HitRecord) -> Option<Scatter> {
+        let mut rng = thread_rng();
+        let unit_direction = ray_in.direction.normalized();
+
+        if !rec.front_face {
+            // The ray travelled through the medium to get here, sample a
+            // free flight distance to see if it scattered on the way
+            let sigma_t = self.sigma_t();
+            let max_distance = rec.t * ray_in.direction.length();
+
+            // Pick a channel to sample the distance from, the other channels
+            // are accounted for by weighting with the averaged pdf
+            let channel = rng.gen_range(0..3);
+            let distance = -(1.0 - rng.gen::<f64>()).ln() / sigma_t[channel];
+
+            if distance < max_distance {
+                let transmittance = transmittance(sigma_t, distance);
+                let pdf = (sigma_t * transmittance).dot(Color::one()) / 3.0;
+                let albedo = self.albedo.color(&rec.tex_coord());
+
+                return Some( Scatter {
+                    scattered: Ray::new(ray_in.origin + unit_direction*distance, Vec3::random_unit()),
+                    attenuation: albedo * sigma_t * transmittance / pdf,
+                    delta: true,
+                });
+            }
+
+            // Paths through so much of the medium that no light survives
+            // would otherwise come out as 0/0
+            let transmittance = transmittance(sigma_t, max_distance);
+            let pdf = transmittance.dot(Color::one()) / 3.0;
+            if pdf <= 0.0 {
+                return None;
+            }
+
+            if self.exit_transmittance(ray_in, rec) <= rng.gen() {
+                return Some( Scatter {
+                    scattered: Ray::new(rec.point, unit_direction.reflect(rec.shading_normal)),
+                    attenuation: transmittance / pdf,
+                    delta: true,
+                });
+            }
+
+            // Out through the rough boundary, cosine weighted about the
+            // outward normal
+            let direction = -rec.shading_normal + Vec3::random_unit();
+            if direction.near_zero() || direction.dot(rec.normal) >= 0.0 {
+                return None;
+            }
+            return Some( Scatter {
+                scattered: Ray::new(rec.point, direction),
+                attenuation: transmittance / pdf,
+                delta: false,
+            });
+        }
+
+        Some( Scatter {
+            scattered: Ray::new(rec.point, interface(unit_direction, rec.shading_normal, 1.0/self.ir, &mut rng)),
+            attenuation: Color::one(),
+            delta: true,
+        })
+    }
+
+    // Light leaving the medium, entering it is a smooth interface
+    fn eval(&self, ray_in: &Ray, rec: &HitRecord, direction: Vec3<f64>) -> Option<(Color, f64)> {
+        if rec.front_face {
+            return None;
+        }
+        let cos = -rec.shading_normal.dot(direction.normalized());
+        if cos <= 0.0 || direction.dot(rec.normal) >= 0.0 {
+            return Some((Color::zero(), 0.0));
+        }
+
+        let transmittance = transmittance(self.sigma_t(), rec.t * ray_in.direction.length());
+        let exit = self.exit_transmittance(ray_in, rec) * cos / PI;
+        let pdf = transmittance.dot(Color::one()) / 3.0 * exit;
+        Some((transmittance * exit, pdf))
+    }
+}
+
+fn transmittance(sigma_t: Color, distance: f64) -> Color {
+    Color::new(
+        (-sigma_t.x * distance).exp(),
+        (-sigma_t.y * distance).exp(),
+        (-sigma_t.z * distance).exp()
+    )
+}
+
+// Smooth dielectric boundary of the medium
+fn interface(unit_direction: Vec3<f64>, normal: Vec3<f64>, refraction_ratio: f64, rng: &mut impl Rng) -> Vec3<f64> {
+    let cos_theta = (-unit_direction).dot(normal).min(1.0);
+    let sin_theta = (1.0 - cos_theta*cos_theta).sqrt();
+
+    if refraction_ratio * sin_theta > 1.0
+        || reflectance(cos_theta, refraction_ratio) > rng.gen() {
+        unit_direction.reflect(normal)
+    } else {
+        unit_direction.refract(normal, refraction_ratio)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::{Hittable, Sphere};
+
+    #[test]
+    fn test_exit_finite() {
+        // Rays leaving the medium from its center, through thin and very
+        // dense media, one channel much denser than the others
+        let paths = [Color::one(), Color::new(1e-3, 1e-3, 1e-3), Color::new(1e-4, 0.5, 2.0)];
+        for mean_free_path in paths {
+            let sphere = Sphere::new(Vec3::zero(), 1.0, Subsurface {
+                albedo: Color::new(0.9, 0.8, 0.7),
+                mean_free_path,
+                ir: 1.4,
+            });
+            let ray = Ray::new(Vec3::zero(), Vec3::new(0.3, 0.8, -0.2));
+            let rec = sphere.hit(&ray, (0.001, f64::INFINITY)).unwrap();
+            assert!(!rec.front_face);
+
+            for _ in 0..2000 {
+                if let Some(scatter) = rec.material.scatter(&ray, &rec) {
+                    // Weights never exceed the number of channels
+                    let a = scatter.attenuation;
+                    for c in [a.x, a.y, a.z] {
+                        assert!(c.is_finite() && (0.0..=3.0).contains(&c), "{c} for {mean_free_path:?}");
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use crate::vector::Color;
+use crate::spectrum;
+use std::f64::consts::PI;
+
+// Thin coating (soap film, anti-reflective lens coating, oxidized metal)
+// whose reflectance varies with wavelength due to interference between
+// light reflected off its top and bottom surfaces
+#[derive(Copy, Clone, Debug)]
+pub struct ThinFilm {
+    // Thickness in nanometres
+    pub thickness: f64,
+    pub ir: f64,
+}
+
+// Wavelengths standing in for the red, green and blue channels
+// when not rendering in spectral mode
+const RGB_WAVELENGTHS: [f64; 3] = [650.0, 550.0, 450.0];
+
+impl ThinFilm {
+    // Reflectance of the film over a dielectric with IOR `substrate_ir`,
+    // for light arriving from a medium with IOR `outside_ir`
+    pub fn reflectance(&self, cos_theta: f64, outside_ir: f64, substrate_ir: f64, wavelength: f64) -> f64 {
+        let Some((cos_film, rs12, rp12)) = self.top_interface(cos_theta, outside_ir) else {
+            return 1.0;
+        };
+
+        let sin_substrate = outside_ir / substrate_ir * (1.0 - cos_theta*cos_theta).sqrt();
+        if sin_substrate >= 1.0 {
+            return 1.0;
+        }
+        let cos_substrate = (1.0 - sin_substrate*sin_substrate).sqrt();
+        let (rs23, rp23) = fresnel_amplitudes(self.ir, substrate_ir, cos_film, cos_substrate);
+
+        let delta = self.phase(cos_film, wavelength);
+        0.5 * (airy(rs12, rs23, delta) + airy(rp12, rp23, delta))
+    }
+
+    // Reflectance of the film over a metal with normal incidence
+    // reflectance `f0`, for light arriving from a medium with IOR `outside_ir`
+    pub fn metal_reflectance(&self, cos_theta: f64, outside_ir: f64, f0: f64, wavelength: f64) -> f64 {
+        let Some((cos_film, rs12, rp12)) = self.top_interface(cos_theta, outside_ir) else {
+            return 1.0;
+        };
+
+        // Metals reflect with close to a half wave phase shift
+        let r23 = -f0.clamp(0.0, 1.0).sqrt();
+        let delta = self.phase(cos_film, wavelength);
+        0.5 * (airy(rs12, r23, delta) + airy(rp12, r23, delta))
+    }
+
+    // Dielectric reflectance per color channel, or at the traced
+    // wavelength in spectral mode
+    pub fn reflectance_rgb(&self, cos_theta: f64, outside_ir: f64, substrate_ir: f64, wavelength: Option<f64>) -> Color {
+        match wavelength {
+            Some(wavelength) => Color::one() * self.reflectance(cos_theta, outside_ir, substrate_ir, wavelength),
+            None => Color::new(
+                self.reflectance(cos_theta, outside_ir, substrate_ir, RGB_WAVELENGTHS[0]),
+                self.reflectance(cos_theta, outside_ir, substrate_ir, RGB_WAVELENGTHS[1]),
+                self.reflectance(cos_theta, outside_ir, substrate_ir, RGB_WAVELENGTHS[2])
+            ),
+        }
+    }
+
+    // Metal reflectance per color channel, or at the traced
+    // wavelength in spectral mode
+    pub fn metal_reflectance_rgb(&self, cos_theta: f64, outside_ir: f64, f0: Color, wavelength: Option<f64>) -> Color {
+        match wavelength {
+            Some(wavelength) => {
+                let f0 = spectrum::upsample(f0, wavelength);
+                Color::one() * self.metal_reflectance(cos_theta, outside_ir, f0, wavelength)
+            },
+            None => Color::new(
+                self.metal_reflectance(cos_theta, outside_ir, f0.x, RGB_WAVELENGTHS[0]),
+                self.metal_reflectance(cos_theta, outside_ir, f0.y, RGB_WAVELENGTHS[1]),
+                self.metal_reflectance(cos_theta, outside_ir, f0.z, RGB_WAVELENGTHS[2])
+            ),
+        }
+    }
+
+    // Cosine inside the film and amplitudes reflected off its top,
+    // None on total internal reflection
+    fn top_interface(&self, cos_theta: f64, outside_ir: f64) -> Option<(f64, f64, f64)> {
+        let sin_film = outside_ir / self.ir * (1.0 - cos_theta*cos_theta).sqrt();
+        if sin_film >= 1.0 {
+            return None;
+        }
+        let cos_film = (1.0 - sin_film*sin_film).sqrt();
+        let (rs, rp) = fresnel_amplitudes(outside_ir, self.ir, cos_theta, cos_film);
+        Some((cos_film, rs, rp))
+    }
+
+    // Phase difference between the two reflected waves
+    fn phase(&self, cos_film: f64, wavelength: f64) -> f64 {
+        4.0 * PI * self.ir * self.thickness * cos_film / wavelength
+    }
+}
+
+// s and p polarized Fresnel amplitude coefficients
+fn fresnel_amplitudes(n1: f64, n2: f64, cos1: f64, cos2: f64) -> (f64, f64) {
+    let rs = (n1*cos1 - n2*cos2) / (n1*cos1 + n2*cos2);
+    let rp = (n2*cos1 - n1*cos2) / (n2*cos1 + n1*cos2);
+    (rs, rp)
+}
+
+// Airy summation of the multiple reflections inside the film
+fn airy(r12: f64, r23: f64, delta: f64) -> f64 {
+    let cross = 2.0 * r12 * r23 * delta.cos();
+    ((r12*r12 + r23*r23 + cross) / (1.0 + r12*r12*r23*r23 + cross)).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Unpolarized Fresnel reflectance of a bare interface
+    fn fresnel(n1: f64, n2: f64, cos1: f64) -> f64 {
+        let sin2 = n1 / n2 * (1.0 - cos1*cos1).sqrt();
+        let cos2 = (1.0 - sin2*sin2).sqrt();
+        let (rs, rp) = fresnel_amplitudes(n1, n2, cos1, cos2);
+        0.5 * (rs*rs + rp*rp)
+    }
+
+    #[test]
+    fn test_zero_thickness() {
+        // A film with no thickness leaves the bare substrate's Fresnel
+        // reflectance, whatever its own index
+        let film = ThinFilm { thickness: 0.0, ir: 1.33 };
+        for cos in [1.0, 0.8, 0.5, 0.2, 0.05] {
+            for wavelength in [450.0, 550.0, 650.0] {
+                let r = film.reflectance(cos, 1.0, 1.5, wavelength);
+                assert!((r - fresnel(1.0, 1.5, cos)).abs() < 1e-12, "{r} at cos {cos}");
+            }
+            let rgb = film.reflectance_rgb(cos, 1.0, 1.5, None);
+            assert!((rgb.x - rgb.z).abs() < 1e-12);
+        }
+
+        // Same over a metal when the film matches the outside medium
+        let film = ThinFilm { thickness: 0.0, ir: 1.0 };
+        for f0 in [0.04, 0.5, 0.95] {
+            assert!((film.metal_reflectance(1.0, 1.0, f0, 550.0) - f0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_interference() {
+        // A quarter wave anti-reflective coating with index sqrt(1.5)
+        // cancels reflection at its design wavelength
+        let ir = 1.5f64.sqrt();
+        let film = ThinFilm { thickness: 550.0 / (4.0 * ir), ir };
+        assert!(film.reflectance(1.0, 1.0, 1.5, 550.0) < 1e-12);
+        assert!(film.reflectance(1.0, 1.0, 1.5, 450.0) > 1e-4);
+    }
+}
@@ -0,0 +1,199 @@
+use crate::vector::{Vec3, Color};
+use crate::environment::{self, Environment};
+use crate::background::Background;
+use crate::texture::ImageTexture;
+use crate::light::Sun;
+use crate::spectrum;
+use std::f64::consts::PI;
+
+// Daylight sky from the analytic model of Preetham, Shirley and Smits
+// (1999), along with the sun lighting it. Directions below the horizon
+// repeat the horizon color.
+#[derive(Copy, Clone, Debug)]
+pub struct PhysicalSky {
+    // Angle of the sun above the horizon, in degrees
+    pub sun_elevation: f64,
+    // Angle of the sun clockwise from -z (towards +x) seen from above,
+    // in degrees
+    pub sun_azimuth: f64,
+    // Haziness of the atmosphere, 2 is a very clear day and 10 is hazy
+    pub turbidity: f64,
+    // Scale from the model's kcd/m² to scene units, the default leaves a
+    // white surface under the midday sun close to 1
+    pub intensity: f64,
+}
+
+// Solar illuminance above the atmosphere, in klux
+const SOLAR_ILLUMINANCE: f64 = 128.0;
+
+// Wavelengths in micrometres standing in for the red, green and blue
+// channels when attenuating sunlight
+const RGB_WAVELENGTHS: [f64; 3] = [0.65, 0.55, 0.45];
+
+impl PhysicalSky {
+    pub fn new(sun_elevation: f64, sun_azimuth: f64, turbidity: f64) -> Self {
+        Self { sun_elevation, sun_azimuth, turbidity, intensity: 0.025 }
+    }
+
+    pub fn sun_direction(&self) -> Vec3<f64> {
+        let elevation = self.sun_elevation.to_radians();
+        let azimuth = self.sun_azimuth.to_radians();
+        Vec3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            -elevation.cos() * azimuth.cos()
+        )
+    }
+
+    // Radiance of the sky (without the sun itself) arriving from the
+    // given direction
+    pub fn radiance(&self, direction: Vec3<f64>) -> Color {
+        let d = direction.normalized();
+        let s = self.sun_direction();
+        let theta_s = self.sun_zenith();
+        let t = self.turbidity;
+
+        // Keep to the upper hemisphere, where the model is defined
+        let cos_theta = d.y.max(1e-3);
+        let gamma = d.dot(s).clamp(-1.0, 1.0).acos();
+
+        let perez = |[a, b, c, d, e]: [f64; 5], cos_theta: f64, gamma: f64| {
+            (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+        };
+        let relative = |coefficients: [f64; 5]| {
+            perez(coefficients, cos_theta, gamma) / perez(coefficients, 1.0, theta_s)
+        };
+
+        let big_y = zenith_luminance(t, theta_s) * relative([
+            0.1787*t - 1.4630,
+            -0.3554*t + 0.4275,
+            -0.0227*t + 5.3251,
+            0.1206*t - 2.5771,
+            -0.0670*t + 0.3703,
+        ]);
+        let x = zenith_chromaticity(t, theta_s, [
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]) * relative([
+            -0.0193*t - 0.2592,
+            -0.0665*t + 0.0008,
+            -0.0004*t + 0.2125,
+            -0.0641*t - 0.8989,
+            -0.0033*t + 0.0452,
+        ]);
+        let y = zenith_chromaticity(t, theta_s, [
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]) * relative([
+            -0.0167*t - 0.2608,
+            -0.0950*t + 0.0092,
+            -0.0079*t + 0.2102,
+            -0.0441*t - 1.6537,
+            -0.0109*t + 0.0529,
+        ]);
+
+        // xyY to XYZ to linear sRGB
+        let xyz = Vec3::new(x / y * big_y, big_y, (1.0 - x - y) / y * big_y);
+        let rgb = spectrum::xyz_to_rgb(xyz) * self.intensity;
+        Color::new(rgb.x.max(0.0), rgb.y.max(0.0), rgb.z.max(0.0))
+    }
+
+    // Sunlight after passing through the atmosphere, attenuated by
+    // Rayleigh scattering and haze (Preetham et al., appendix A.2)
+    pub fn sun(&self) -> Sun {
+        let direction = self.sun_direction();
+        if self.sun_elevation <= 0.0 {
+            return Sun::new(direction, Color::zero());
+        }
+
+        // Relative optical mass of the air the light passes through
+        let theta_s = self.sun_zenith();
+        let m = 1.0 / (theta_s.cos() + 0.15 * (93.885 - theta_s.to_degrees()).powf(-1.253));
+        let beta = 0.04608 * self.turbidity - 0.04586;
+        let transmittance = |l: f64| {
+            let rayleigh = (-0.008735 * l.powf(-4.08) * m).exp();
+            let aerosol = (-beta * l.powf(-1.3) * m).exp();
+            rayleigh * aerosol
+        };
+
+        let color = Color::new(
+            transmittance(RGB_WAVELENGTHS[0]),
+            transmittance(RGB_WAVELENGTHS[1]),
+            transmittance(RGB_WAVELENGTHS[2])
+        );
+        Sun::new(direction, color * (SOLAR_ILLUMINANCE * self.intensity))
+    }
+
+    // Bakes the sky into a latitude-longitude map `width` texels wide, so
+    // it can be importance sampled like any other environment
+    pub fn environment(&self, width: u32) -> Environment {
+        let height = (width / 2).max(1);
+        let mut texels = Vec::with_capacity((width * height * 3) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                // Image rows run top to bottom, v from the bottom up
+                let u = (x as f64 + 0.5) / width as f64;
+                let v = 1.0 - (y as f64 + 0.5) / height as f64;
+                let c = self.radiance(environment::direction_from_uv(u, v));
+                texels.extend_from_slice(&[c.x as f32, c.y as f32, c.z as f32]);
+            }
+        }
+        Environment::new(ImageTexture::new(texels, vec![], width, height))
+    }
+
+    fn sun_zenith(&self) -> f64 {
+        (PI / 2.0 - self.sun_elevation.to_radians()).clamp(0.0, PI / 2.0)
+    }
+}
+
+// Evaluated directly, without the importance sampling of a baked map
+impl Background for PhysicalSky {
+    fn color(&self, direction: Vec3<f64>) -> Color {
+        self.radiance(direction)
+    }
+}
+
+// Luminance straight up, in kcd/m²
+fn zenith_luminance(turbidity: f64, theta_s: f64) -> f64 {
+    let chi = (4.0 / 9.0 - turbidity / 120.0) * (PI - 2.0 * theta_s);
+    (4.0453 * turbidity - 4.9710) * chi.tan() - 0.2155 * turbidity + 2.4192
+}
+
+// Chromaticity straight up, from rows of cubic coefficients in the sun's
+// zenith angle for turbidity², turbidity and 1
+fn zenith_chromaticity(turbidity: f64, theta_s: f64, rows: [[f64; 4]; 3]) -> f64 {
+    let powers = [theta_s.powi(3), theta_s.powi(2), theta_s, 1.0];
+    let cubic = |row: [f64; 4]| row.iter().zip(powers).map(|(c, p)| c * p).sum::<f64>();
+    turbidity * turbidity * cubic(rows[0]) + turbidity * cubic(rows[1]) + cubic(rows[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_radiance() {
+        let sky = PhysicalSky::new(45.0, 90.0, 3.0);
+        let zenith = sky.radiance(Vec3::new(0.0, 1.0, 0.0));
+        // Clear skies are blue overhead
+        assert!(zenith.z > zenith.x);
+
+        // Brighter around the sun than away from it
+        let s = sky.sun_direction();
+        let near = sky.radiance(s + Vec3::new(0.0, 0.1, 0.0));
+        let far = sky.radiance(Vec3::new(-s.x, s.y, -s.z));
+        assert!(near.y > far.y);
+    }
+
+    #[test]
+    fn test_sun() {
+        let noon = PhysicalSky::new(80.0, 0.0, 3.0).sun().irradiance;
+        let sunset = PhysicalSky::new(5.0, 0.0, 3.0).sun().irradiance;
+        assert!(noon.y > sunset.y);
+        // More of the blue is scattered away when the sun is low
+        assert!(sunset.z / sunset.x < noon.z / noon.x);
+        assert_eq!(PhysicalSky::new(-5.0, 0.0, 3.0).sun().irradiance.y, 0.0);
+    }
+}
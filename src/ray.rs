@@ -1,20 +1,98 @@
 use crate::vector::{Vec3, Color};
 use crate::scene::Scene;
-use crate::shape::Hittable;
+use crate::shape::{Hittable, HitRecord};
 
-use crate::texture::Texture;
-use crate::shape::Sphere;
-use crate::material::Metal;
+use crate::spectrum;
+
+use rand::{thread_rng, Rng};
 
 #[derive(Copy, Clone, Debug)]
 pub struct Ray {
     pub origin: Vec3<f64>,
     pub direction: Vec3<f64>,
+    // Set when tracing a single wavelength (in nm) in spectral mode
+    pub wavelength: Option<f64>,
+    // Rays through the neighbouring pixels, used to filter textures
+    pub differential: Option<RayDifferential>,
+}
+
+// Offset rays one pixel over in x and y
+#[derive(Copy, Clone, Debug)]
+pub struct RayDifferential {
+    pub rx_origin: Vec3<f64>,
+    pub rx_direction: Vec3<f64>,
+    pub ry_origin: Vec3<f64>,
+    pub ry_direction: Vec3<f64>,
 }
 
 impl Ray {
     pub fn new(origin: Vec3<f64>, direction: Vec3<f64>) -> Self {
-        Self { origin, direction }
+        Self { origin, direction, wavelength: None, differential: None }
+    }
+
+    // Shrinks the offset rays towards this one, with several samples per
+    // pixel each only needs to cover part of the pixel
+    pub fn scale_differentials(&mut self, scale: f64) {
+        if let Some(rd) = &mut self.differential {
+            rd.rx_origin = self.origin + (rd.rx_origin - self.origin) * scale;
+            rd.ry_origin = self.origin + (rd.ry_origin - self.origin) * scale;
+            rd.rx_direction = self.direction + (rd.rx_direction - self.direction) * scale;
+            rd.ry_direction = self.direction + (rd.ry_direction - self.direction) * scale;
+        }
+    }
+
+    // Mirror reflection off the hit surface, carrying the differentials
+    // over so textures seen in the reflection stay filtered
+    pub fn reflected(&self, rec: &HitRecord, direction: Vec3<f64>) -> Ray {
+        let differential = rec.differentials.zip(self.differential).map(|(d, rd)| {
+            let n = rec.shading_normal;
+            let wo = -self.direction.normalized();
+            let wi = direction.normalized();
+            let dndx = rec.dndu*d.dudx + rec.dndv*d.dvdx;
+            let dndy = rec.dndu*d.dudy + rec.dndv*d.dvdy;
+            let dwodx = -rd.rx_direction.normalized() - wo;
+            let dwody = -rd.ry_direction.normalized() - wo;
+            let ddndx = dwodx.dot(n) + wo.dot(dndx);
+            let ddndy = dwody.dot(n) + wo.dot(dndy);
+
+            RayDifferential {
+                rx_origin: rec.point + d.dpdx,
+                rx_direction: wi - dwodx + (dndx*wo.dot(n) + n*ddndx) * 2.0,
+                ry_origin: rec.point + d.dpdy,
+                ry_direction: wi - dwody + (dndy*wo.dot(n) + n*ddndy) * 2.0,
+            }
+        });
+        Ray { origin: rec.point, direction, wavelength: self.wavelength, differential }
+    }
+
+    // Refraction through the hit surface, where refraction_ratio is the
+    // IOR on the incoming side over the IOR on the far side
+    pub fn refracted(&self, rec: &HitRecord, direction: Vec3<f64>, refraction_ratio: f64) -> Ray {
+        let differential = rec.differentials.zip(self.differential).map(|(d, rd)| {
+            let eta = refraction_ratio;
+            let n = rec.shading_normal;
+            let wo = -self.direction.normalized();
+            let wi = direction.normalized();
+            let dndx = rec.dndu*d.dudx + rec.dndv*d.dvdx;
+            let dndy = rec.dndu*d.dudy + rec.dndv*d.dvdy;
+            let dwodx = -rd.rx_direction.normalized() - wo;
+            let dwody = -rd.ry_direction.normalized() - wo;
+            let ddndx = dwodx.dot(n) + wo.dot(dndx);
+            let ddndy = dwody.dot(n) + wo.dot(dndy);
+
+            let cos_o = wo.dot(n);
+            let cos_i = wi.dot(n).abs().max(1e-8);
+            let mu = eta*cos_o - cos_i;
+            let dmu = eta - eta*eta*cos_o / cos_i;
+
+            RayDifferential {
+                rx_origin: rec.point + d.dpdx,
+                rx_direction: wi - dwodx*eta + dndx*mu + n*(dmu*ddndx),
+                ry_origin: rec.point + d.dpdy,
+                ry_direction: wi - dwody*eta + dndy*mu + n*(dmu*ddndy),
+            }
+        });
+        Ray { origin: rec.point, direction, wavelength: self.wavelength, differential }
     }
 
     pub fn at(&self, t: f64) -> Vec3<f64> {
@@ -22,38 +100,124 @@ impl Ray {
     }
 
     pub fn color(&self, scene: &Scene, depth: i32) -> Color {
+        self.trace(scene, depth, None, true)
+    }
+
+    // bsdf_pdf is the density the previous bounce sampled this ray with,
+    // when lights were also sampled directly there and the two
+    // strategies have to be weighted against each other. from_camera is
+    // set until the first bounce.
+    fn trace(&self, scene: &Scene, depth: i32, bsdf_pdf: Option<f64>, from_camera: bool) -> Color {
         // Reached max depth
         if depth <= 0 {
             return Color::zero();
         }
 
         // Check if ray hits any other objects in scene
-        if let Some(hit_record) = scene.hit(self, (0.001, f64::INFINITY)) {
-            let emitted = hit_record.material.emitted(hit_record.u, hit_record.v);
+        if let Some(mut hit_record) = scene.hit(self, (0.001, f64::INFINITY)) {
+            hit_record.differentials = hit_record.compute_differentials(self);
+
+            // Skip past transparent parts of alpha masked surfaces
+            let opacity = hit_record.material.opacity(&hit_record);
+            if opacity < 1.0 && opacity <= thread_rng().gen() {
+                return Ray { origin: hit_record.point, ..*self }.trace(scene, depth, bsdf_pdf, from_camera);
+            }
+
+            let emitted = self.spectral(hit_record.material.emitted(self, &hit_record));
+            let direct = self.sample_background(scene, &hit_record) + self.sample_sun(scene, &hit_record);
             if let Some(scatter) = hit_record.material.scatter(self, &hit_record) {
-                return emitted + scatter.attenuation * scatter.scattered.color(scene, depth-1);
+                // Only materials that know how their rays spread keep the
+                // differentials, everything else drops them
+                let scattered = Ray { wavelength: self.wavelength, ..scatter.scattered };
+                // Delta lobes can't be sampled by direct lighting, so rays
+                // from them reaching a light aren't weighted against it
+                let pdf = if scatter.delta {
+                    None
+                } else {
+                    hit_record.material.eval(self, &hit_record, scattered.direction).map(|(_, pdf)| pdf)
+                };
+                return emitted + direct + self.spectral(scatter.attenuation) * scattered.trace(scene, depth-1, pdf, false);
             } else {
-                return emitted;
+                return emitted + direct;
             }
         }
 
         // Background color
-        let unit_direction = self.direction.normalized();
-        match &scene.skybox {
-            Some(sky) => {
-                let (u, v) = Sphere::<Metal>::get_uv(unit_direction);
-                let c = sky.color(u, v);
-                Color::new(
-                    c.x * c.x,
-                    c.y * c.y,
-                    c.z * c.z
-                )
-            },
-            None => {
-                scene.background
-            },
+        let visible = if from_camera { scene.background_visible } else { scene.background_lighting };
+        if !visible {
+            return Color::zero();
+        }
+        let weight = match bsdf_pdf {
+            Some(pdf) => power_heuristic(pdf, scene.background.pdf(self.direction)),
+            None => 1.0,
+        };
+        self.spectral(scene.background.color(self.direction)) * weight
+    }
+
+    // Light arriving directly from a sampled background direction,
+    // for materials that can be evaluated
+    fn sample_background(&self, scene: &Scene, rec: &HitRecord) -> Color {
+        if !scene.background_lighting {
+            return Color::zero();
+        }
+        let Some((direction, radiance, pdf)) = scene.background.sample() else {
+            return Color::zero();
+        };
+        let Some((f, bsdf_pdf)) = rec.material.eval(self, rec, direction) else {
+            return Color::zero();
+        };
+        if f.near_zero() || !unoccluded(scene, rec.point, direction) {
+            return Color::zero();
         }
+
+        self.spectral(f) * self.spectral(radiance) * (power_heuristic(pdf, bsdf_pdf) / pdf)
     }
+
+    // Light arriving straight from the sun, which only direct sampling
+    // can reach
+    fn sample_sun(&self, scene: &Scene, rec: &HitRecord) -> Color {
+        let Some(sun) = &scene.sun else {
+            return Color::zero();
+        };
+        let Some((f, _)) = rec.material.eval(self, rec, sun.direction) else {
+            return Color::zero();
+        };
+        if f.near_zero() || !unoccluded(scene, rec.point, sun.direction) {
+            return Color::zero();
+        }
+
+        self.spectral(f) * self.spectral(sun.irradiance)
+    }
+
+    // Converts an RGB quantity to its value at the traced wavelength,
+    // stored in all three channels
+    fn spectral(&self, c: Color) -> Color {
+        match self.wavelength {
+            Some(wavelength) => Color::one() * spectrum::upsample(c, wavelength),
+            None => c,
+        }
+    }
+}
+
+// Whether nothing blocks the way from origin along direction, letting
+// shadow rays through transparent parts of alpha masked surfaces
+fn unoccluded(scene: &Scene, origin: Vec3<f64>, direction: Vec3<f64>) -> bool {
+    let mut ray = Ray::new(origin, direction);
+    while let Some(rec) = scene.hit(&ray, (0.001, f64::INFINITY)) {
+        let opacity = rec.material.opacity(&rec);
+        if opacity >= 1.0 || opacity > thread_rng().gen() {
+            return false;
+        }
+        ray.origin = rec.point;
+    }
+    true
+}
+
+// Weight for a sample from a strategy with density `pdf` when another
+// strategy with density `other` could also have produced it
+fn power_heuristic(pdf: f64, other: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other * other);
+    if a + b == 0.0 { 0.0 } else { a / (a + b) }
 }
 
 #[cfg(test)]
@@ -85,4 +249,116 @@ mod tests {
         assert_eq!(p.y, 1.0);
         assert_eq!(p.z, 1.5);
     }
+
+    // Scene of the given objects with nothing else lighting it, a black
+    // background that doesn't count as a light
+    fn dark_scene(objects: Vec<Box<dyn Hittable>>) -> Scene {
+        use crate::background::Gradient;
+        use crate::camera::Perspective;
+
+        let camera = Perspective::new(Vec3::new(0.0, 1.0, 1.0), Vec3::zero(), Vec3::new(0.0, 1.0, 0.0), 40.0, 1.0, 0.0, 1.0);
+        let black = Gradient { bottom: Color::zero(), top: Color::zero() };
+        let mut scene = Scene::new(Box::new(camera), objects, Box::new(black));
+        scene.background_lighting = false;
+        scene
+    }
+
+    // A ground plane cut out by a 1x1 mask of the given alpha, over a
+    // light
+    fn cutout_over_light(alpha: f32) -> Scene {
+        use crate::material::{Cutout, DiffuseLight, Lambertian};
+        use crate::shape::Plane;
+        use crate::texture::ImageTexture;
+
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        dark_scene(vec![
+            Box::new(Plane::ground(Cutout {
+                material: Lambertian { albedo: Color::one() },
+                mask: ImageTexture::new(vec![1.0; 3], vec![alpha], 1, 1),
+                threshold: 0.5,
+            })),
+            Box::new(Plane::new(-up, up, DiffuseLight::new(Color::one() * 0.5))),
+        ])
+    }
+
+    #[test]
+    fn test_cutout() {
+        // Camera rays go straight through cut out parts to the light
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert_eq!(ray.color(&cutout_over_light(0.0), 5), Color::one() * 0.5);
+        assert_eq!(ray.color(&cutout_over_light(1.0), 5), Color::zero());
+    }
+
+    #[test]
+    fn test_cutout_shadow() {
+        // Shadow rays pass through cut out parts as well
+        let origin = Vec3::new(0.0, -0.5, 0.0);
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        assert!(unoccluded(&cutout_over_light(0.0), origin, up));
+        assert!(!unoccluded(&cutout_over_light(1.0), origin, up));
+    }
+
+    #[test]
+    fn test_background_visibility() {
+        use crate::material::Lambertian;
+        use crate::shape::Plane;
+
+        // White background over a grey ground, seen directly by a ray going
+        // up and lighting the ground for a ray going down
+        let color = |visible: bool, lighting: bool, direction: Vec3<f64>| {
+            let mut scene = dark_scene(vec![Box::new(Plane::ground(Lambertian { albedo: Color::one() * 0.5 }))]);
+            scene.background = Box::new(Color::one());
+            scene.background_visible = visible;
+            scene.background_lighting = lighting;
+            Ray::new(Vec3::new(0.0, 1.0, 0.0), direction).color(&scene, 5)
+        };
+        let (up, down) = (Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+
+        // Hidden from the camera but still lighting the scene
+        assert_eq!(color(false, true, up), Color::zero());
+        assert_eq!(color(false, true, down), Color::one() * 0.5);
+        // Seen by the camera without lighting anything
+        assert_eq!(color(true, false, up), Color::one());
+        assert_eq!(color(true, false, down), Color::zero());
+    }
+
+    #[test]
+    fn test_sunlight() {
+        use crate::light::Sun;
+        use crate::material::{Material, Principled, Metal, Coated, Lambertian};
+        use crate::shape::{Plane, Sphere};
+
+        fn plane(material: impl Material + Send + Sync + 'static) -> Box<dyn Hittable> {
+            Box::new(Plane::ground(material))
+        }
+
+        // Glossy and coated surfaces lit only by the sun, which sits just
+        // off the mirror direction of the camera ray
+        let lit = |surface: Box<dyn Hittable>, blocked: bool| {
+            let mut objects = vec![surface];
+            if blocked {
+                objects.push(Box::new(Sphere::new(Vec3::new(0.0, 0.5, -0.6), 0.2, Lambertian { albedo: Color::one() })));
+            }
+            let mut scene = dark_scene(objects);
+            scene.sun = Some(Sun::new(Vec3::new(0.0, 1.0, -1.2), Color::one() * 3.0));
+            // One bounce, so all the light comes from sampling the sun
+            Ray::new(Vec3::new(0.0, 1.0, 1.0), Vec3::new(0.0, -1.0, -1.0)).color(&scene, 1)
+        };
+
+        let surfaces = || {
+            let mut clearcoat = Principled::new(Color::new(0.2, 0.4, 0.8));
+            clearcoat.clearcoat = std::sync::Arc::new(1.0);
+            [
+                ("principled", plane(Principled::new(Color::new(0.8, 0.4, 0.2)))),
+                ("clearcoat", plane(clearcoat)),
+                ("fuzzy metal", plane(Metal::new(Color::new(0.9, 0.8, 0.6), 0.3))),
+                ("coated", plane(Coated::new(Lambertian { albedo: Color::new(0.5, 0.5, 0.5) }, 1.5))),
+            ]
+        };
+        for ((name, surface), (_, same)) in surfaces().into_iter().zip(surfaces()) {
+            let c = lit(surface, false);
+            assert!(c.x > 0.01 && c.x.is_finite(), "{name} isn't lit by the sun: {c:?}");
+            assert_eq!(lit(same, true), Color::zero(), "{name} is lit through the blocker");
+        }
+    }
 }
@@ -7,25 +7,36 @@ pub mod cube;
 pub mod dark;
 use crate::shape::{Hittable, HitRecord};
 use crate::ray::Ray;
-use crate::Camera;
-use crate::texture::ImageTexture;
-use crate::vector::Color;
+use crate::camera::Camera;
+use crate::background::Background;
+use crate::light::Sun;
 
 pub struct Scene {
-    pub camera: Camera,
+    pub camera: Box<dyn Camera>,
     pub objects: Vec<Box<dyn Hittable>>,
-    pub skybox: Option<ImageTexture>,
-    pub background: Color,
+    pub background: Box<dyn Background>,
+    // Whether camera rays see the background, otherwise they get black
+    pub background_visible: bool,
+    // Whether the background lights the scene
+    pub background_lighting: bool,
+    pub sun: Option<Sun>,
 }
 
 impl Scene {
-    pub fn new(camera: Camera, objects: Vec<Box<dyn Hittable>>, skybox: Option<ImageTexture>, background: Color) -> Self {
-        Self { camera, objects, skybox, background }
+    pub fn new(camera: Box<dyn Camera>, objects: Vec<Box<dyn Hittable>>, background: Box<dyn Background>) -> Self {
+        Self {
+            camera,
+            objects,
+            background,
+            background_visible: true,
+            background_lighting: true,
+            sun: None
+        }
     }
 }
 
 impl Hittable for Scene {
-    fn hit(&self, ray: &Ray, (t_min, t_max): (f64, f64)) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, (t_min, t_max): (f64, f64)) -> Option<HitRecord<'_>> {
         let mut result = None;
         let mut closest_so_far = t_max;
 
@@ -1,16 +1,16 @@
 use crate::scene::Scene;
 use crate::vector::{Vec3, Color};
-use crate::material::{Lambertian, DiffuseLight, Metal, Dielectric};
-use crate::texture::{Checker, ImageTexture, SolidColor};
+use crate::material::{Lambertian, DiffuseLight};
+use crate::texture::{Checker, SolidColor};
 use crate::shape::{Hittable, Sphere, Plane};
-use crate::Camera;
+use crate::camera::Perspective;
 
 pub fn gen_scene() -> Scene {
     let lookfrom = Vec3::new(0.0, 0.0, 3.0);
     let lookat = Vec3::<f64>::zero();
     let vup = Vec3::new(0.0, 1.0, 1.0);
     let aspect_ratio = 3.0 / 2.0;
-    let cam = Camera::new(
+    let cam = Perspective::new(
         lookfrom,
         lookat,
         vup,
@@ -20,13 +20,13 @@ pub fn gen_scene() -> Scene {
         5.0
     );
 
-    let mut objects: Vec<Box<dyn Hittable>> = Vec::new();
-    objects.push(Box::new(Sphere::new(
+    let objects: Vec<Box<dyn Hittable>> = vec![
+        Box::new(Sphere::new(
                 Vec3::new(0.0, 1.0, 0.0),
                 0.5,
-                DiffuseLight { emit: SolidColor { color: Color::new(4.0, 4.0, 4.0) } }
-    )));
-    objects.push(Box::new(Plane::new(
+                DiffuseLight::new(SolidColor { color: Color::new(4.0, 4.0, 4.0) })
+        )),
+        Box::new(Plane::new(
                 Vec3::new(0.0, -1.0, 0.0),
                 Vec3::new(0.0, 1.0, 0.0),
                 Lambertian {
@@ -36,6 +36,7 @@ pub fn gen_scene() -> Scene {
                         scale: 0.5,
                     }
                 }
-    )));
-    Scene::new(cam, objects, None, Color::zero())
+        )),
+    ];
+    Scene::new(Box::new(cam), objects, Box::new(Color::zero()))
 }
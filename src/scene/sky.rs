@@ -1,16 +1,16 @@
 use crate::scene::Scene;
+use crate::environment::Environment;
 use crate::vector::{Vec3, Color};
 use crate::material::Metal;
-use crate::texture::ImageTexture;
 use crate::shape::{Hittable, Sphere};
-use crate::Camera;
+use crate::camera::Perspective;
 
 pub fn gen_scene() -> Scene {
     let lookfrom = Vec3::new(1.0, 0.2, 3.0);
     let lookat = Vec3::<f64>::zero();
     let vup = Vec3::new(0.0, 1.0, 0.0);
     let aspect_ratio = 3.0 / 2.0;
-    let cam = Camera::new(
+    let cam = Perspective::new(
         lookfrom,
         lookat,
         vup,
@@ -20,24 +20,19 @@ pub fn gen_scene() -> Scene {
         5.0
     );
 
-    let mut objects: Vec<Box<dyn Hittable>> = Vec::new();
-    objects.push(Box::new(Sphere::new(
+    let objects: Vec<Box<dyn Hittable>> = vec![
+        Box::new(Sphere::new(
                 Vec3::new(0.0, -1000.0, 0.0),
                 1000.0,
                 //Lambertian { albedo: SolidColor { color: Color::new(0.8, 0.8, 0.8) } }
-                Metal {
-                    albedo: Color::new(1.0, 1.0, 1.0),
-                    fuzz: 0.0,
-                }
-    )));
-    objects.push(Box::new(Sphere::new(
+                Metal::new(Color::new(1.0, 1.0, 1.0), 0.0)
+        )),
+        Box::new(Sphere::new(
                 Vec3::new(0.0, 0.5, 0.0),
                 0.5,
-                Metal {
-                    albedo: Color::new(1.0, 1.0, 1.0),
-                    fuzz: 0.0,
-                }
-    )));
-    let skybox = ImageTexture::load(String::from("./assets/textures/sky.png"));
-    Scene::new(cam, objects, Some(skybox), Color::zero())
+                Metal::new(Color::new(1.0, 1.0, 1.0), 0.0)
+        )),
+    ];
+    let skybox = Environment::load(String::from("./assets/textures/sky.png"));
+    Scene::new(Box::new(cam), objects, Box::new(skybox))
 }
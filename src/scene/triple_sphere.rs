@@ -2,15 +2,16 @@ use crate::scene::Scene;
 use crate::vector::{Vec3, Color};
 use crate::material::{Lambertian, DiffuseLight};
 use crate::texture::{Checker, UVTexture, ImageTexture};
+use crate::environment::Environment;
 use crate::shape::{Hittable, Sphere};
-use crate::Camera;
+use crate::camera::Perspective;
 
 pub fn gen_scene() -> Scene {
     let lookfrom = Vec3::new(0.0, 1.0, -5.0);
     let lookat = Vec3::<f64>::zero();
     let vup = Vec3::new(0.0, 1.0, 0.0);
     let aspect_ratio = 3.0 / 2.0;
-    let cam = Camera::new(
+    let cam = Perspective::new(
         lookfrom,
         lookat,
         vup,
@@ -20,13 +21,13 @@ pub fn gen_scene() -> Scene {
         5.0
     );
 
-    let mut objects: Vec<Box<dyn Hittable>> = Vec::new();
-    objects.push(Box::new(Sphere::new(
+    let objects: Vec<Box<dyn Hittable>> = vec![
+        Box::new(Sphere::new(
                 Vec3::new(0.0, 0.0, 0.0),
                 0.5,
-                DiffuseLight { emit: UVTexture {} }
-    )));
-    objects.push(Box::new(Sphere::new(
+                DiffuseLight::new(UVTexture {})
+        )),
+        Box::new(Sphere::new(
                 Vec3::new(1.0, 0.0, 0.5),
                 0.5,
                 Lambertian { albedo: Checker {
@@ -34,12 +35,13 @@ pub fn gen_scene() -> Scene {
                     even_color: Color::new(0.9, 0.9, 0.9),
                     scale: 32.0,
                 }}
-    )));
-    objects.push(Box::new(Sphere::new(
+        )),
+        Box::new(Sphere::new(
                 Vec3::new(-1.0, 0.0, 0.5),
                 0.5,
                 Lambertian { albedo: ImageTexture::load(String::from("./assets/textures/earth.jpg")) }
-    )));
-    let skybox = ImageTexture::load(String::from("./assets/textures/stars.jpg"));
-    Scene::new(cam, objects, Some(skybox), Color::zero())
+        )),
+    ];
+    let skybox = Environment::load(String::from("./assets/textures/stars.jpg"));
+    Scene::new(Box::new(cam), objects, Box::new(skybox))
 }
@@ -3,7 +3,7 @@ use crate::vector::{Vec3, Color};
 use crate::material::{Lambertian, Metal, Dielectric};
 use crate::texture::{SolidColor, Checker};
 use crate::shape::{Hittable, Sphere};
-use crate::Camera;
+use crate::camera::Perspective;
 
 use rand::{thread_rng, Rng};
 
@@ -15,7 +15,7 @@ pub fn gen_scene() -> Scene {
     let lookat = Vec3::new(0.0, 0.0, 0.0);
     let vup = Vec3::new(0.0, 1.0, 0.0);
     let aspect_ratio = 3.0 / 2.0;
-    let cam = Camera::new(
+    let cam = Perspective::new(
         lookfrom,
         lookat,
         vup,
@@ -63,19 +63,14 @@ pub fn gen_scene() -> Scene {
                     objects.push(Box::new(Sphere::new(
                         center,
                         0.2,
-                        Metal {
-                            albedo: Color::random_range(0.5, 1.0),
-                            fuzz: rng.gen_range(0.0..0.5)
-                        }
+                        Metal::new(Color::random_range(0.5, 1.0), rng.gen_range(0.0..0.5))
                     )));
                 } else {
                     // glass
                     objects.push(Box::new(Sphere::new(
                         center,
                         0.2,
-                        Dielectric {
-                            ir: 1.5
-                        }
+                        Dielectric::new(1.5)
                     )));
                 }
             }
@@ -84,9 +79,7 @@ pub fn gen_scene() -> Scene {
     objects.push(Box::new(Sphere::new(
         Vec3::new(0.0, 1.0, 0.0),
         1.0,
-        Dielectric {
-            ir: 1.5
-        }
+        Dielectric::new(1.5)
     )));
     objects.push(Box::new(Sphere::new(
         Vec3::new(-4.0, 1.0, 0.0),
@@ -98,10 +91,7 @@ pub fn gen_scene() -> Scene {
     objects.push(Box::new(Sphere::new(
         Vec3::new(4.0, 1.0, 0.0),
         1.0,
-        Metal {
-            albedo: Color::new(0.7, 0.6, 0.5),
-            fuzz: 0.0
-        }
+        Metal::new(Color::new(0.7, 0.6, 0.5), 0.0)
     )));
-    Scene::new(cam, objects, None, Color::zero())
+    Scene::new(Box::new(cam), objects, Box::new(Color::zero()))
 }
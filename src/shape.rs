@@ -10,11 +10,28 @@ pub use self::plane::Plane;
 use crate::material::Material;
 use crate::vector::Vec3;
 use crate::ray::Ray;
+use crate::texture::TexCoord;
 
 #[derive(Copy, Clone)]
 pub struct HitRecord<'material> {
     pub point: Vec3<f64>,
+    // Geometric normal, facing the incoming ray. Decides which side of
+    // the surface a direction is on.
     pub normal: Vec3<f64>,
+    // Normal to shade with, interpolated from vertex normals or perturbed
+    // by normal and bump maps. On the same side as the geometric normal.
+    pub shading_normal: Vec3<f64>,
+    // Surface tangents along the u and v texture directions, not
+    // normalized (used for normal and bump mapping)
+    pub dpdu: Vec3<f64>,
+    pub dpdv: Vec3<f64>,
+    // Change of the normal along u and v, zero for flat shapes
+    pub dndu: Vec3<f64>,
+    pub dndv: Vec3<f64>,
+    // Set by the integrator when the incoming ray carries differentials
+    pub differentials: Option<Differentials>,
+    // Hit point relative to the shape, for solid textures
+    pub local_point: Vec3<f64>,
     pub material: &'material dyn Material,
     pub t: f64,
     pub u: f64,
@@ -22,6 +39,65 @@ pub struct HitRecord<'material> {
     pub front_face: bool,
 }
 
+// How the hit point and texture coordinates change between
+// neighbouring pixels, used to size texture filters
+#[derive(Copy, Clone, Debug)]
+pub struct Differentials {
+    pub dpdx: Vec3<f64>,
+    pub dpdy: Vec3<f64>,
+    pub dudx: f64,
+    pub dvdx: f64,
+    pub dudy: f64,
+    pub dvdy: f64,
+}
+
+impl HitRecord<'_> {
+    pub fn tex_coord(&self) -> TexCoord {
+        let d = self.differentials;
+        TexCoord {
+            u: self.u,
+            v: self.v,
+            point: self.point,
+            local_point: self.local_point,
+            duvdx: d.map_or((0.0, 0.0), |d| (d.dudx, d.dvdx)),
+            duvdy: d.map_or((0.0, 0.0), |d| (d.dudy, d.dvdy)),
+        }
+    }
+
+    // Intersects the ray's offset rays with the tangent plane at the hit
+    // point, then expresses the offsets in terms of u and v
+    pub fn compute_differentials(&self, ray: &Ray) -> Option<Differentials> {
+        let rd = ray.differential?;
+        let n = self.normal;
+        let d = n.dot(self.point);
+
+        let tx = (d - n.dot(rd.rx_origin)) / n.dot(rd.rx_direction);
+        let ty = (d - n.dot(rd.ry_origin)) / n.dot(rd.ry_direction);
+        if !tx.is_finite() || !ty.is_finite() {
+            return None;
+        }
+        let dpdx = rd.rx_origin + rd.rx_direction*tx - self.point;
+        let dpdy = rd.ry_origin + rd.ry_direction*ty - self.point;
+
+        // Least squares solve of dpdx = dudx*dpdu + dvdx*dpdv
+        let a = self.dpdu.dot(self.dpdu);
+        let b = self.dpdu.dot(self.dpdv);
+        let c = self.dpdv.dot(self.dpdv);
+        let det = a*c - b*b;
+        let solve = |dp: Vec3<f64>| {
+            if det.abs() < 1e-12 {
+                return (0.0, 0.0);
+            }
+            let (pu, pv) = (self.dpdu.dot(dp), self.dpdv.dot(dp));
+            ((c*pu - b*pv) / det, (a*pv - b*pu) / det)
+        };
+        let (dudx, dvdx) = solve(dpdx);
+        let (dudy, dvdy) = solve(dpdy);
+
+        Some(Differentials { dpdx, dpdy, dudx, dvdx, dudy, dvdy })
+    }
+}
+
 pub trait Hittable: Send + Sync {
-    fn hit(&self, ray: &Ray, t_range: (f64, f64)) -> Option<HitRecord>;
+    fn hit(&self, ray: &Ray, t_range: (f64, f64)) -> Option<HitRecord<'_>>;
 }
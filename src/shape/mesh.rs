@@ -95,7 +95,7 @@ impl<M: Material + Clone> Mesh<M> {
 }
 
 impl<M: Material + Send + Sync> Hittable for Mesh<M> {
-    fn hit(&self, ray: &Ray, (t_min, t_max): (f64, f64)) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, (t_min, t_max): (f64, f64)) -> Option<HitRecord<'_>> {
         let mut result = None;
         let mut closest = t_max;
 
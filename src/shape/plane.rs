@@ -15,8 +15,24 @@ impl<M: Material> Plane<M> {
     }
 }
 
+// Fixture shared by the material tests
+#[cfg(test)]
+impl<M: Material + Send + Sync> Plane<M> {
+    // Level ground through the origin, facing +y
+    pub(crate) fn ground(material: M) -> Self {
+        Plane::new(Vec3::zero(), Vec3::new(0.0, 1.0, 0.0), material)
+    }
+
+    // Ray from `from` to the origin, and where it hits
+    pub(crate) fn hit_from(&self, from: Vec3<f64>) -> (Ray, HitRecord<'_>) {
+        let ray = Ray::new(from, -from);
+        let rec = self.hit(&ray, (0.001, f64::INFINITY)).expect("ray misses the plane");
+        (ray, rec)
+    }
+}
+
 impl<M: Material + Send + Sync> Hittable for Plane<M> {
-    fn hit(&self, ray: &Ray, (t_min, t_max): (f64, f64)) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, (t_min, t_max): (f64, f64)) -> Option<HitRecord<'_>> {
         // This function feels messy
         let d = self.normal.dot(ray.direction);
         if d.abs() > 1e-6 {
@@ -34,10 +50,18 @@ impl<M: Material + Send + Sync> Hittable for Plane<M> {
                 let v = e2.dot(point);
 
                 let front_face = ray.direction.dot(self.normal) < 0.0;
+                let normal = if front_face { self.normal} else { -self.normal};
 
                 Some(HitRecord {
                     point,
-                    normal: if front_face { self.normal} else { -self.normal},
+                    normal,
+                    shading_normal: normal,
+                    dpdu: e1,
+                    dpdv: e2,
+                    dndu: Vec3::zero(),
+                    dndv: Vec3::zero(),
+                    differentials: None,
+                    local_point: point - self.position,
                     material: &self.material,
                     t,
                     u,
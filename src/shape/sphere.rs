@@ -20,10 +20,26 @@ impl<M: Material> Sphere<M> {
 
         (phi / (2.0*std::f64::consts::PI), theta / std::f64::consts::PI)
     }
+
+    // Derivatives of the surface point along u and v, given the outward
+    // unit normal p
+    pub fn get_tangents(p: Vec3<f64>, radius: f64) -> (Vec3<f64>, Vec3<f64>) {
+        let pi = std::f64::consts::PI;
+        let sin_theta = (1.0 - p.y*p.y).max(0.0).sqrt();
+
+        let dpdu = Vec3::new(p.z, 0.0, -p.x) * (2.0*pi*radius);
+        let dpdv = if sin_theta > 1e-8 {
+            Vec3::new(-p.x*p.y / sin_theta, sin_theta, -p.y*p.z / sin_theta) * (pi*radius)
+        } else {
+            // Poles, any direction in the tangent plane will do
+            Vec3::new(pi*radius, 0.0, 0.0)
+        };
+        (dpdu, dpdv)
+    }
 }
 
 impl<M: Material + Send + Sync> Hittable for Sphere<M> {
-    fn hit(&self, ray: &Ray, (t_min, t_max): (f64, f64)) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, (t_min, t_max): (f64, f64)) -> Option<HitRecord<'_>> {
         let oc = ray.origin - self.center;
         let a = ray.direction.length_squared();
         let half_b = oc.dot(ray.direction);
@@ -45,9 +61,19 @@ impl<M: Material + Send + Sync> Hittable for Sphere<M> {
             let outward_normal = (p - self.center) / self.radius;
             let front_face = ray.direction.dot(outward_normal) < 0.0;
             let (u, v) = Sphere::<M>::get_uv(outward_normal);
+            let (dpdu, dpdv) = Sphere::<M>::get_tangents(outward_normal, self.radius);
+            let normal = if front_face { outward_normal } else { -outward_normal };
             return Some(HitRecord {
                 point: p,
-                normal: if front_face { outward_normal } else { -outward_normal },
+                normal,
+                shading_normal: normal,
+                dpdu,
+                dpdv,
+                // The normal is (p - center)/radius
+                dndu: if front_face { dpdu / self.radius } else { -dpdu / self.radius },
+                dndv: if front_face { dpdv / self.radius } else { -dpdv / self.radius },
+                differentials: None,
+                local_point: p - self.center,
                 material: &self.material,
                 t: root,
                 u,
@@ -63,7 +89,7 @@ impl<M: Material + Send + Sync> Hittable for Sphere<M> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::vectors::Color;
+    use crate::vector::Color;
     use crate::material::Lambertian;
     use crate::texture::SolidColor;
 
@@ -111,7 +137,26 @@ mod tests {
                 assert_eq!(rec.t, 0.5);
                 assert!(rec.front_face);
             },
-            None => assert!(false),
+            None => panic!("expected a hit"),
+        }
+    }
+
+    #[test]
+    fn test_tangents() {
+        // Moving along dpdu or dpdv changes only u or only v, at the rate
+        // of one unit per unit of the tangent
+        let radius = 2.0;
+        let eps = 1e-6;
+        for p in [Vec3::new(0.6, 0.0, 0.8), Vec3::new(-0.3, 0.5, 0.4), Vec3::new(0.2, -0.7, -0.5)] {
+            let p = p.normalized();
+            let (dpdu, dpdv) = Sphere::<Lambertian<Color>>::get_tangents(p, radius);
+            assert!(dpdu.dot(p).abs() < 1e-12 && dpdv.dot(p).abs() < 1e-12);
+
+            let (u, v) = Sphere::<Lambertian<Color>>::get_uv(p);
+            let (u1, v1) = Sphere::<Lambertian<Color>>::get_uv((p + dpdu * (eps / radius)).normalized());
+            assert!((u1 - u - eps).abs() < 1e-9 && (v1 - v).abs() < 1e-9);
+            let (u2, v2) = Sphere::<Lambertian<Color>>::get_uv((p + dpdv * (eps / radius)).normalized());
+            assert!((u2 - u).abs() < 1e-9 && (v2 - v - eps).abs() < 1e-9);
         }
     }
 }
@@ -14,17 +14,38 @@ impl<M: Material> Triangle<M> {
     pub fn new(vertices: [Vec3<f64>; 3], normals: Option<[Vec3<f64>; 3]>, texture_cords: Option<[(f64, f64); 3]>, material: M) -> Self {
         Self { vertices, normals, texture_cords, material }
     }
+
+    // Derivatives of the surface point along u and v, from the texture
+    // coordinates when present and an arbitrary edge aligned frame otherwise
+    pub fn get_tangents(&self, normal: Vec3<f64>) -> (Vec3<f64>, Vec3<f64>) {
+        let dp1 = self.vertices[1] - self.vertices[0];
+        let dp2 = self.vertices[2] - self.vertices[0];
+
+        if let Some(vert_tex) = self.texture_cords {
+            let (du1, dv1) = (vert_tex[1].0 - vert_tex[0].0, vert_tex[1].1 - vert_tex[0].1);
+            let (du2, dv2) = (vert_tex[2].0 - vert_tex[0].0, vert_tex[2].1 - vert_tex[0].1);
+            let det = du1*dv2 - dv1*du2;
+            if det.abs() > 1e-12 {
+                return (
+                    (dp1*dv2 - dp2*dv1) / det,
+                    (dp2*du1 - dp1*du2) / det
+                );
+            }
+        }
+
+        (dp1, normal.cross(dp1))
+    }
 }
 
 impl<M: Material + Send + Sync> Hittable for Triangle<M> {
-    fn hit(&self, ray: &Ray, (t_min, t_max): (f64, f64)) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, (t_min, t_max): (f64, f64)) -> Option<HitRecord<'_>> {
         // Ray-triangle intersection from scratchapixel
         let v0v1 = self.vertices[1] - self.vertices[0];
         let v0v2 = self.vertices[2] - self.vertices[0];
         let p = ray.direction.cross(v0v2);
         let det = v0v1.dot(p);
 
-        if det.abs() < std::f64::EPSILON {
+        if det.abs() < f64::EPSILON {
             return None;
         }
 
@@ -32,7 +53,7 @@ impl<M: Material + Send + Sync> Hittable for Triangle<M> {
 
         let s = ray.origin - self.vertices[0];
         let b = s.dot(p) * inv_det;
-        if b < 0.0 || b > 1.0 {
+        if !(0.0..=1.0).contains(&b) {
             return None;
         }
 
@@ -47,13 +68,16 @@ impl<M: Material + Send + Sync> Hittable for Triangle<M> {
         if t > t_min && t < t_max {
             let point = ray.origin + (ray.direction * t);
             let a = 1.0 - b - c;
-            let normal = match self.normals {
+            let face_normal = v0v1.cross(v0v2).normalized();
+            let (normal, shading_normal) = match self.normals {
                 Some(vert_norms) => {
-                    (vert_norms[0]*a + vert_norms[1]*b + vert_norms[2]*c).normalized()
+                    // Vertex normals decide which side is outside, whatever
+                    // the winding
+                    let shading_normal = (vert_norms[0]*a + vert_norms[1]*b + vert_norms[2]*c).normalized();
+                    let normal = if face_normal.dot(shading_normal) < 0.0 { -face_normal } else { face_normal };
+                    (normal, shading_normal)
                 },
-                None => {
-                    v0v1.cross(v0v2).normalized()
-                }
+                None => (face_normal, face_normal)
             };
             let (u, v) = match self.texture_cords {
                 Some(vert_tex) => {
@@ -64,10 +88,18 @@ impl<M: Material + Send + Sync> Hittable for Triangle<M> {
                 },
                 None => (0.0, 0.0)
             };
+            let (dpdu, dpdv) = self.get_tangents(shading_normal);
             let front_face = ray.direction.dot(normal) < 0.0;
             Some(HitRecord {
                 point,
                 normal: if front_face { normal } else { -normal },
+                shading_normal: if front_face { shading_normal } else { -shading_normal },
+                dpdu,
+                dpdv,
+                dndu: Vec3::zero(),
+                dndv: Vec3::zero(),
+                differentials: None,
+                local_point: point,
                 material: &self.material,
                 t,
                 u,
@@ -83,7 +115,7 @@ impl<M: Material + Send + Sync> Hittable for Triangle<M> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::vectors::Color;
+    use crate::vector::Color;
     use crate::material::Lambertian;
     use crate::texture::SolidColor;
 
@@ -145,7 +177,29 @@ mod tests {
                 assert_eq!(rec.t, 1.0);
                 assert!(rec.front_face);
             },
-            None => assert!(false),
+            None => panic!("expected a hit"),
         }
     }
+
+    #[test]
+    fn test_tangents() {
+        let vertices = [Vec3::new(0.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 1.0), Vec3::new(0.5, 1.0, 0.0)];
+        let normal = (vertices[1] - vertices[0]).cross(vertices[2] - vertices[0]).normalized();
+        let material = Lambertian { albedo: Color::one() };
+
+        // The tangents rebuild the edges from their change in (u, v)
+        let uvs = [(0.1, 0.2), (0.9, 0.3), (0.4, 0.8)];
+        let t = Triangle::new(vertices, None, Some(uvs), material.clone());
+        let (dpdu, dpdv) = t.get_tangents(normal);
+        for i in [1, 2] {
+            let (du, dv) = (uvs[i].0 - uvs[0].0, uvs[i].1 - uvs[0].1);
+            assert!((dpdu * du + dpdv * dv - (vertices[i] - vertices[0])).length() < 1e-12);
+        }
+
+        // Without texture coordinates any frame in the triangle's plane
+        let t = Triangle::new(vertices, None, None, material);
+        let (dpdu, dpdv) = t.get_tangents(normal);
+        assert!(dpdu.dot(normal).abs() < 1e-12 && dpdv.dot(normal).abs() < 1e-12);
+        assert!(!dpdu.cross(dpdv).near_zero());
+    }
 }
@@ -0,0 +1,248 @@
+use crate::vector::{Vec3, Color};
+
+use std::sync::OnceLock;
+
+// Visible range sampled in spectral mode, in nanometres
+pub const WAVELENGTH_MIN: f64 = 360.0;
+pub const WAVELENGTH_MAX: f64 = 830.0;
+
+// Maps a uniform random number in [0, 1) to a wavelength, importance
+// sampled towards where the eye is most sensitive (Radziszewski et al.)
+pub fn sample_wavelength(r: f64) -> f64 {
+    538.0 - 138.888889 * (0.85691062 - 1.82750197 * r).atanh()
+}
+
+// Probability density of sample_wavelength
+pub fn wavelength_pdf(wavelength: f64) -> f64 {
+    if !(WAVELENGTH_MIN..=WAVELENGTH_MAX).contains(&wavelength) {
+        return 0.0;
+    }
+    0.003939804 / (0.0072 * (wavelength - 538.0)).cosh().powi(2)
+}
+
+// CIE 1931 color matching functions using the multi-lobe gaussian fit
+// from Wyman, Sloan and Shirley (2013)
+pub fn cie_xyz(wavelength: f64) -> Vec3<f64> {
+    fn g(x: f64, mu: f64, sigma1: f64, sigma2: f64) -> f64 {
+        let t = (x - mu) / if x < mu { sigma1 } else { sigma2 };
+        (-0.5 * t * t).exp()
+    }
+
+    let l = wavelength;
+    Vec3::new(
+        1.056*g(l, 599.8, 37.9, 31.0) + 0.362*g(l, 442.0, 16.0, 26.7) - 0.065*g(l, 501.1, 20.4, 26.2),
+        0.821*g(l, 568.8, 46.9, 40.5) + 0.286*g(l, 530.9, 16.3, 31.1),
+        1.217*g(l, 437.0, 11.8, 36.0) + 0.681*g(l, 459.0, 26.0, 13.8)
+    )
+}
+
+// CIE XYZ to linear sRGB (D65)
+pub fn xyz_to_rgb(xyz: Vec3<f64>) -> Color {
+    Color::new(
+        3.2404542*xyz.x - 1.5371385*xyz.y - 0.4985314*xyz.z,
+        -0.9692660*xyz.x + 1.8760108*xyz.y + 0.0415560*xyz.z,
+        0.0556434*xyz.x - 0.2040259*xyz.y + 1.0572252*xyz.z
+    )
+}
+
+// sRGB transfer function, encoded [0, 1] value to linear light
+pub fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// Inverse sRGB transfer function (OETF), linear light to encoded value
+pub fn linear_to_srgb(c: f64) -> f64 {
+    if c <= 0.0031308 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// Smooth red, green and blue basis spectra summing to one everywhere,
+// so white upsamples to a flat spectrum and reflectances stay in [0, 1]
+fn basis(wavelength: f64) -> Color {
+    fn smoothstep(edge0: f64, edge1: f64, x: f64) -> f64 {
+        let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    let b = 1.0 - smoothstep(490.0, 510.0, wavelength);
+    let r = smoothstep(580.0, 600.0, wavelength);
+    Color::new(r, 1.0 - r - b, b)
+}
+
+// Value at the given wavelength of the spectrum an RGB color upsamples to
+pub fn upsample(color: Color, wavelength: f64) -> f64 {
+    basis(wavelength).dot(color)
+}
+
+// RGB contribution of radiance carried at a single wavelength picked by
+// sample_wavelength. Normalized so that upsampling a color and projecting
+// it back gives the original color on average.
+pub fn wavelength_to_rgb(wavelength: f64) -> Color {
+    let pdf = wavelength_pdf(wavelength);
+    if pdf == 0.0 {
+        return Color::zero();
+    }
+    let m = projection();
+    let c = xyz_to_rgb(cie_xyz(wavelength)) / pdf;
+    Color::new(m[0].dot(c), m[1].dot(c), m[2].dot(c))
+}
+
+// Linear RGB color of a blackbody radiator at the given temperature in
+// Kelvin, normalized to unit luminance
+pub fn blackbody(temperature: f64) -> Color {
+    assert!(temperature > 0.0, "blackbody temperature must be positive, got {temperature}");
+
+    // Planck's law, constant factors drop out when normalizing. Scaled by
+    // the exponential at the longest wavelength so cold bodies don't
+    // underflow to 0/0.
+    let exponent = |l: f64| 0.014387769 / (l * temperature);
+    let offset = exponent(WAVELENGTH_MAX * 1e-9);
+    let planck = |wavelength: f64| {
+        let l = wavelength * 1e-9;
+        let x = exponent(l);
+        (offset - x).exp() / (l.powi(5) * -(-x).exp_m1())
+    };
+
+    let mut xyz = Vec3::zero();
+    let mut wavelength = WAVELENGTH_MIN;
+    while wavelength <= WAVELENGTH_MAX {
+        xyz += cie_xyz(wavelength) * planck(wavelength);
+        wavelength += 5.0;
+    }
+
+    let rgb = xyz_to_rgb(xyz / xyz.y);
+    Color::new(rgb.x.max(0.0), rgb.y.max(0.0), rgb.z.max(0.0))
+}
+
+// Rec. 709 luminance of a linear RGB color
+pub fn luminance(color: Color) -> f64 {
+    0.2126*color.x + 0.7152*color.y + 0.0722*color.z
+}
+
+// Per channel gains making a blackbody light of the given temperature
+// appear neutral, relative to a D65 (6504K) white point
+pub fn white_balance(temperature: f64) -> Color {
+    let reference = blackbody(6504.0);
+    let source = blackbody(temperature);
+    // Very low temperatures have next to no blue, limit the gain there
+    let gain = Color::new(
+        reference.x / source.x.max(0.05),
+        reference.y / source.y.max(0.05),
+        reference.z / source.z.max(0.05)
+    );
+    gain / luminance(gain)
+}
+
+fn projection() -> &'static [Vec3<f64>; 3] {
+    static PROJECTION: OnceLock<[Vec3<f64>; 3]> = OnceLock::new();
+    PROJECTION.get_or_init(|| {
+        // Integral over the visible range of each color matching function
+        // times each basis spectrum, inverted so the round trip is exact
+        let steps = (WAVELENGTH_MAX - WAVELENGTH_MIN) as usize;
+        let mut m = [Vec3::zero(); 3];
+        for i in 0..steps {
+            let wavelength = WAVELENGTH_MIN + i as f64 + 0.5;
+            let c = xyz_to_rgb(cie_xyz(wavelength));
+            let b = basis(wavelength);
+            for (row, channel) in m.iter_mut().zip([c.x, c.y, c.z]) {
+                *row += b * channel;
+            }
+        }
+        invert(m)
+    })
+}
+
+fn invert(m: [Vec3<f64>; 3]) -> [Vec3<f64>; 3] {
+    // Columns of the inverse are the cross products of the rows
+    let c0 = m[1].cross(m[2]);
+    let c1 = m[2].cross(m[0]);
+    let c2 = m[0].cross(m[1]);
+    let det = m[0].dot(c0);
+    [
+        Vec3::new(c0.x, c1.x, c2.x) / det,
+        Vec3::new(c0.y, c1.y, c2.y) / det,
+        Vec3::new(c0.z, c1.z, c2.z) / det,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let colors = [
+            Color::one(),
+            Color::new(1.0, 0.0, 0.0),
+            Color::new(0.2, 0.5, 0.9),
+        ];
+        let steps = (WAVELENGTH_MAX - WAVELENGTH_MIN) as usize;
+        for c in colors {
+            let mut sum = Color::zero();
+            for i in 0..steps {
+                let wavelength = WAVELENGTH_MIN + i as f64 + 0.5;
+                let pdf = wavelength_pdf(wavelength);
+                sum += wavelength_to_rgb(wavelength) * upsample(c, wavelength) * pdf;
+            }
+            assert!((sum - c).length() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_sample_wavelength() {
+        for i in 0..100 {
+            let wavelength = sample_wavelength(i as f64 / 100.0);
+            assert!((WAVELENGTH_MIN..=WAVELENGTH_MAX).contains(&wavelength));
+        }
+    }
+
+    #[test]
+    fn test_blackbody() {
+        let warm = blackbody(2700.0);
+        assert!(warm.x > warm.y && warm.y > warm.z);
+
+        let cool = blackbody(10000.0);
+        assert!(cool.z > cool.x);
+
+        assert!((luminance(warm) - 1.0).abs() < 0.05);
+
+        // Very cold and very hot bodies stay finite
+        for temperature in [1.0, 50.0, 1e6] {
+            let c = blackbody(temperature);
+            assert!(c.x.is_finite() && c.y.is_finite() && c.z.is_finite());
+            let gain = white_balance(temperature);
+            assert!(gain.x.is_finite() && gain.y.is_finite() && gain.z.is_finite());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_blackbody_zero() {
+        blackbody(0.0);
+    }
+
+    #[test]
+    fn test_srgb_round_trip() {
+        for i in 0..=20 {
+            let c = i as f64 / 20.0;
+            assert!((linear_to_srgb(srgb_to_linear(c)) - c).abs() < 1e-12);
+        }
+        // Mid grey encodes to roughly the familiar 188/255
+        assert!((linear_to_srgb(0.5) * 255.0 - 187.5).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_upsample_white() {
+        for i in 0..=40 {
+            let wavelength = WAVELENGTH_MIN + 10.0 * i as f64;
+            assert!((upsample(Color::one(), wavelength) - 1.0).abs() < 1e-12);
+        }
+    }
+}
@@ -2,12 +2,85 @@ pub mod solid_color;
 pub mod image_texture;
 pub mod checker;
 pub mod uv;
+pub mod blackbody;
+pub mod perlin;
+pub mod cellular;
+pub mod noise;
+pub mod marble;
+pub mod wood;
+pub mod worley;
 pub use self::solid_color::SolidColor;
-pub use self::image_texture::ImageTexture;
-pub use self::checker::Checker;
+pub use self::image_texture::{ImageTexture, ColorSpace, Filter, Wrap};
+pub use self::checker::{Checker, SolidChecker};
 pub use self::uv::UVTexture;
-use crate::vector::Color;
+pub use self::blackbody::Blackbody;
+pub use self::perlin::Perlin;
+pub use self::cellular::Cellular;
+pub use self::noise::{Noise, Turbulence};
+pub use self::marble::Marble;
+pub use self::wood::Wood;
+pub use self::worley::Worley;
+use crate::vector::{Vec3, Color};
 
-pub trait Texture {
-    fn color(&self, u: f64, v: f64) -> Color;
+// Where a texture is being looked up
+#[derive(Copy, Clone, Debug)]
+pub struct TexCoord {
+    pub u: f64,
+    pub v: f64,
+    // Hit point in world space
+    pub point: Vec3<f64>,
+    // Hit point relative to the shape, so patterns move with it
+    pub local_point: Vec3<f64>,
+    // Change of (u, v) to the neighbouring pixels in x and y, zero when
+    // unknown. Image textures use it to pick a mip level.
+    pub duvdx: (f64, f64),
+    pub duvdy: (f64, f64),
+}
+
+impl TexCoord {
+    // Lookup from surface coordinates alone (e.g. skyboxes)
+    pub fn uv(u: f64, v: f64) -> Self {
+        Self {
+            u,
+            v,
+            point: Vec3::zero(),
+            local_point: Vec3::zero(),
+            duvdx: (0.0, 0.0),
+            duvdy: (0.0, 0.0),
+        }
+    }
+
+    pub fn position(&self, space: Space) -> Vec3<f64> {
+        match space {
+            Space::Uv => Vec3::new(self.u, self.v, 0.0),
+            Space::World => self.point,
+            Space::Object => self.local_point,
+        }
+    }
+}
+
+// Domain a solid (3D) texture is evaluated in. World patterns stay put
+// while shapes move through them, Object ones are attached to the shape
+// and move with it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Space {
+    // Surface coordinates as (u, v, 0)
+    Uv,
+    World,
+    Object,
+}
+
+pub trait Texture: Send + Sync {
+    fn color(&self, coord: &TexCoord) -> Color;
+
+    // Scalar lookup for textures driving single valued parameters
+    // (roughness, metallic, etc.), read from the first channel
+    fn value(&self, coord: &TexCoord) -> f64 {
+        self.color(coord).x
+    }
+
+    // Opacity in [0, 1], only image textures carry an alpha channel
+    fn alpha(&self, _coord: &TexCoord) -> f64 {
+        1.0
+    }
 }
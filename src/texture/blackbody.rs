@@ -0,0 +1,23 @@
+use crate::texture::{Texture, TexCoord};
+use crate::vector::Color;
+use crate::spectrum;
+
+// Color of an ideal blackbody radiator, normalized to unit luminance so
+// it can drive DiffuseLight::emit with brightness set by the intensity
+#[derive(Clone)]
+pub struct Blackbody {
+    color: Color,
+}
+
+impl Blackbody {
+    // Temperature in Kelvin, e.g. 2700 for tungsten or 6500 for daylight
+    pub fn new(temperature: f64) -> Self {
+        Self { color: spectrum::blackbody(temperature) }
+    }
+}
+
+impl Texture for Blackbody {
+    fn color(&self, _coord: &TexCoord) -> Color {
+        self.color
+    }
+}
@@ -0,0 +1,73 @@
+use crate::vector::Vec3;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+const POINT_COUNT: usize = 256;
+
+// Feature point generator for cellular noise, one randomly placed point
+// per unit cell. The same seed always gives the same points.
+#[derive(Clone)]
+pub struct Cellular {
+    offsets: Vec<Vec3<f64>>,
+    perm: Vec<usize>,
+}
+
+impl Cellular {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let offsets = (0..POINT_COUNT).map(|_| Vec3::new(rng.gen(), rng.gen(), rng.gen())).collect();
+        let mut perm: Vec<usize> = (0..POINT_COUNT).collect();
+        perm.shuffle(&mut rng);
+        Self { offsets, perm }
+    }
+
+    // Distance to the nearest feature point
+    pub fn distance(&self, p: Vec3<f64>) -> f64 {
+        let (i, j, k) = (p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64);
+
+        let mut nearest = f64::INFINITY;
+        for di in -1..=1 {
+            for dj in -1..=1 {
+                for dk in -1..=1 {
+                    let (ci, cj, ck) = (i + di, j + dj, k + dk);
+                    let feature = Vec3::new(ci as f64, cj as f64, ck as f64) + self.offsets[self.hash(ci, cj, ck)];
+                    nearest = nearest.min((feature - p).length_squared());
+                }
+            }
+        }
+
+        nearest.sqrt()
+    }
+
+    fn hash(&self, i: i64, j: i64, k: i64) -> usize {
+        let h = self.perm[(i & 255) as usize] as i64;
+        let h = self.perm[((h + j) & 255) as usize] as i64;
+        self.perm[((h + k) & 255) as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deterministic() {
+        let a = Cellular::new(7);
+        let b = Cellular::new(7);
+        let p = Vec3::new(1.3, -2.7, 0.4);
+        assert_eq!(a.distance(p), b.distance(p));
+    }
+
+    #[test]
+    fn test_range() {
+        // The nearest point is never further than the far corner of the
+        // neighbouring cells
+        let cellular = Cellular::new(3);
+        for i in 0..1000 {
+            let t = i as f64 * 0.173;
+            let d = cellular.distance(Vec3::new(t, t * 0.7, -t * 1.3));
+            assert!((0.0..=3f64.sqrt()).contains(&d));
+        }
+    }
+}
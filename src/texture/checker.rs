@@ -1,4 +1,4 @@
-use crate::texture::Texture;
+use crate::texture::{Texture, TexCoord, Space};
 use crate::vector::Color;
 
 #[derive(Clone)]
@@ -9,14 +9,144 @@ pub struct Checker {
 }
 
 impl Texture for Checker {
-    fn color(&self, u: f64, v: f64) -> Color {
-        let s = (u * self.scale).floor();
-        let t = (v * self.scale).floor();
+    fn color(&self, coord: &TexCoord) -> Color {
+        let s = coord.u * self.scale;
+        let t = coord.v * self.scale;
+        let ds = coord.duvdx.0.abs().max(coord.duvdy.0.abs()) * self.scale;
+        let dt = coord.duvdx.1.abs().max(coord.duvdy.1.abs()) * self.scale;
 
-        if (s + t) % 2.0 == 0.0 {
+        // Point sample when the footprint stays inside one square
+        if (s - ds).floor() == (s + ds).floor() && (t - dt).floor() == (t + dt).floor() {
+            return if (s.floor() + t.floor()) % 2.0 == 0.0 {
+                self.even_color
+            } else {
+                self.odd_color
+            };
+        }
+
+        // Otherwise box filter over the footprint, using the integral of
+        // the odd squares along each axis
+        let odd_s = odd_fraction(s, ds);
+        let odd_t = odd_fraction(t, dt);
+        let odd = if ds > 1.0 || dt > 1.0 {
+            0.5
+        } else {
+            odd_s + odd_t - 2.0*odd_s*odd_t
+        };
+        self.even_color * (1.0 - odd) + self.odd_color * odd
+    }
+}
+
+// Fraction of [x - dx, x + dx] where floor(x) is odd
+fn odd_fraction(x: f64, dx: f64) -> f64 {
+    // Integral from 0 to x of the function that is 1 where floor(x) is odd
+    fn integral(x: f64) -> f64 {
+        let half = x / 2.0;
+        half.floor() + 2.0 * (half - half.floor() - 0.5).max(0.0)
+    }
+
+    if dx > 0.0 {
+        (integral(x + dx) - integral(x - dx)) / (2.0 * dx)
+    } else {
+        x.floor().rem_euclid(2.0)
+    }
+}
+
+// Checkerboard of unit cubes, so the pattern runs through the shape
+// instead of being stretched over its surface
+#[derive(Clone)]
+pub struct SolidChecker {
+    pub odd_color: Color,
+    pub even_color: Color,
+    pub scale: f64,
+    pub space: Space,
+}
+
+impl Texture for SolidChecker {
+    fn color(&self, coord: &TexCoord) -> Color {
+        let p = coord.position(self.space) * self.scale;
+        let sum = p.x.floor() + p.y.floor() + p.z.floor();
+
+        if sum.rem_euclid(2.0) == 0.0 {
             self.even_color
         } else {
             self.odd_color
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::ray::Ray;
+    use crate::shape::{Hittable, Sphere};
+    use crate::vector::Vec3;
+
+    fn checker() -> Checker {
+        Checker {
+            odd_color: Color::one(),
+            even_color: Color::zero(),
+            scale: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_point_sample() {
+        let checker = checker();
+        assert_eq!(checker.color(&TexCoord::uv(0.5, 0.5)).x, 0.0);
+        assert_eq!(checker.color(&TexCoord::uv(1.5, 0.5)).x, 1.0);
+        assert_eq!(checker.color(&TexCoord::uv(-0.5, 0.5)).x, 1.0);
+    }
+
+    #[test]
+    fn test_filtered() {
+        let checker = checker();
+        // Straddling the edge between an even and an odd square
+        let mut coord = TexCoord::uv(1.0, 0.5);
+        coord.duvdx = (0.25, 0.0);
+        assert!((checker.color(&coord).x - 0.5).abs() < 1e-12);
+
+        // Footprints covering many squares average to grey
+        coord.duvdx = (3.0, 3.0);
+        assert!((checker.color(&coord).x - 0.5).abs() < 1e-12);
+    }
+
+    fn solid_checker(space: Space) -> SolidChecker {
+        SolidChecker {
+            odd_color: Color::one(),
+            even_color: Color::zero(),
+            scale: 1.0,
+            space,
+        }
+    }
+
+    #[test]
+    fn test_solid_ignores_uv() {
+        let checker = solid_checker(Space::World);
+        let mut coord = TexCoord::uv(0.2, 0.3);
+        coord.point = Vec3::new(1.5, 0.5, 0.5);
+        let color = checker.color(&coord);
+        for (u, v) in [(0.7, 0.3), (5.5, -2.0)] {
+            coord.u = u;
+            coord.v = v;
+            assert_eq!(checker.color(&coord), color);
+        }
+        coord.point.x = 2.5;
+        assert_ne!(checker.color(&coord), color);
+    }
+
+    #[test]
+    fn test_object_space() {
+        // The same spot on a sphere moved by one cube keeps its color in
+        // object space, while world space shifts the pattern under it
+        let color = |space: Space, x: f64| {
+            let sphere = Sphere::new(Vec3::new(x, 0.0, 0.0), 0.5, Lambertian { albedo: solid_checker(space) });
+            let ray = Ray::new(Vec3::new(x + 0.1, 0.1, 5.0), Vec3::new(0.0, 0.0, -1.0));
+            let rec = sphere.hit(&ray, (0.001, f64::INFINITY)).unwrap();
+            sphere.material.albedo.color(&rec.tex_coord())
+        };
+        assert_eq!(color(Space::Object, 0.0), color(Space::Object, 1.0));
+        assert_ne!(color(Space::World, 0.0), color(Space::World, 1.0));
+    }
+}
@@ -1,48 +1,476 @@
-use crate::texture::Texture;
+use crate::texture::{Texture, TexCoord};
 use crate::vector::Color;
-use image::GenericImageView;
-use num::clamp;
+use crate::spectrum;
+use image::{ColorType, GenericImageView, ImageFormat};
+use image::codecs::hdr::HdrDecoder;
+use std::fs::File;
+use std::io::BufReader;
 
 #[derive(Clone)]
 pub struct ImageTexture {
-    pub image: Vec<u8>,
-    pub width: u32,
-    pub height: u32,
+    // Texels and size are only set by new, which builds the mip pyramid
+    // from them
+    image: Vec<f32>,
+    alpha: Vec<f32>,
+    width: u32,
+    height: u32,
+    pub filter: Filter,
+    // Handling of coordinates outside [0, 1] along u and along v
+    pub wrap_u: Wrap,
+    pub wrap_v: Wrap,
+    // Rest of the mip pyramid below the full size image, each level half
+    // the size of the one before
+    levels: Vec<MipLevel>,
+}
+
+#[derive(Clone)]
+struct MipLevel {
+    image: Vec<f32>,
+    alpha: Vec<f32>,
+    width: u32,
+    height: u32,
+}
+
+// Borrowed texels of one mip level, the full size image included
+#[derive(Copy, Clone)]
+struct MipView<'a> {
+    image: &'a [f32],
+    alpha: &'a [f32],
+    width: u32,
+    height: u32,
+}
+
+impl MipLevel {
+    fn view(&self) -> MipView<'_> {
+        MipView { image: &self.image, alpha: &self.alpha, width: self.width, height: self.height }
+    }
+}
+
+impl MipView<'_> {
+    // Box filters 2x2 blocks of texels. An odd last column or row folds
+    // into the block before it, which then averages 3 texels across.
+    fn downsample(&self) -> MipLevel {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        // Texels of a row or column of `size` covered by block i of n
+        let span = |i: u32, n: u32, size: u32| 2*i..if i == n - 1 { size } else { 2*i + 2 };
+        let average = |data: &[f32], channels: usize| {
+            let mut out = Vec::with_capacity((width * height) as usize * channels);
+            for y in 0..height {
+                let ys = span(y, height, self.height);
+                for x in 0..width {
+                    let xs = span(x, width, self.width);
+                    let count = (xs.len() * ys.len()) as f32;
+                    for c in 0..channels {
+                        let mut sum = 0.0;
+                        for sy in ys.clone() {
+                            for sx in xs.clone() {
+                                sum += data[(sy * self.width + sx) as usize * channels + c];
+                            }
+                        }
+                        out.push(sum / count);
+                    }
+                }
+            }
+            out
+        };
+
+        MipLevel {
+            image: average(self.image, 3),
+            alpha: if self.alpha.is_empty() { vec![] } else { average(self.alpha, 1) },
+            width,
+            height,
+        }
+    }
+}
+
+// How texels are reconstructed between pixel centres
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Filter {
+    Nearest,
+    Bilinear,
+    // Catmull-Rom, sharper than bilinear when magnified
+    Bicubic,
+}
+
+// How the stored values of an image map to linear light
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ColorSpace {
+    // Color images as saved by most painting tools
+    Srgb,
+    // Data such as normal, bump or roughness maps
+    Linear,
+}
+
+impl ColorSpace {
+    fn decode(&self, value: f32) -> f32 {
+        match self {
+            ColorSpace::Srgb => spectrum::srgb_to_linear(value as f64) as f32,
+            ColorSpace::Linear => value,
+        }
+    }
+}
+
+// How texture coordinates outside [0, 1] are handled
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Wrap {
+    Repeat,
+    // Repeat, flipping every other tile so edges line up
+    Mirror,
+    // Stretch the edge pixels
+    Clamp,
+}
+
+impl Wrap {
+    // Maps a texel index onto [0, size)
+    fn apply(&self, i: i64, size: i64) -> i64 {
+        match self {
+            Wrap::Repeat => i.rem_euclid(size),
+            Wrap::Mirror => {
+                let m = i.rem_euclid(2 * size);
+                if m < size { m } else { 2 * size - 1 - m }
+            },
+            Wrap::Clamp => i.clamp(0, size - 1),
+        }
+    }
 }
 
 impl ImageTexture {
+    // Loads a color image stored as sRGB
     pub fn load(filepath: String) -> Self {
+        ImageTexture::load_as(filepath, ColorSpace::Srgb)
+    }
+
+    // Floating point images (.hdr, .exr) are always read as linear,
+    // whatever the color space given
+    pub fn load_as(filepath: String, color_space: ColorSpace) -> Self {
+       // The generic loader clamps Radiance files to 8 bits
+       if ImageFormat::from_path(&filepath).ok() == Some(ImageFormat::Hdr) {
+           return ImageTexture::load_hdr(filepath);
+       }
+
        let image_file = image::open(filepath).unwrap(); 
        let width = image_file.dimensions().0;
        let height = image_file.dimensions().1;
+       let has_alpha = image_file.color().has_alpha();
+       let color_space = match image_file.color() {
+           ColorType::Rgb32F | ColorType::Rgba32F => ColorSpace::Linear,
+           _ => color_space,
+       };
 
-       let mut image: Vec<u8> = vec![];
-       for y in 0..height {
-           for x in 0..width {
-               let p = image_file.get_pixel(x, y);
-               image.push(p.0[0]);
-               image.push(p.0[1]);
-               image.push(p.0[2]);
+       let pixels = image_file.to_rgba32f();
+       let mut image: Vec<f32> = vec![];
+       let mut alpha: Vec<f32> = vec![];
+       for p in pixels.pixels() {
+           image.push(color_space.decode(p.0[0]));
+           image.push(color_space.decode(p.0[1]));
+           image.push(color_space.decode(p.0[2]));
+           // Alpha is always stored linearly
+           if has_alpha {
+               alpha.push(p.0[3]);
            }
        }
 
-       ImageTexture {
-           image,
-           width,
-           height
-       }
+       ImageTexture::new(image, alpha, width, height)
+    }
+
+    fn load_hdr(filepath: String) -> Self {
+        let reader = BufReader::new(File::open(filepath).unwrap());
+        let decoder = HdrDecoder::new(reader).unwrap();
+        let meta = decoder.metadata();
+        let image = decoder.read_image_hdr().unwrap()
+            .iter()
+            .flat_map(|p| p.0)
+            .collect();
+        ImageTexture::new(image, vec![], meta.width, meta.height)
+    }
+
+    // Linear RGB texels row by row from the top, alpha may be left empty
+    pub fn new(image: Vec<f32>, alpha: Vec<f32>, width: u32, height: u32) -> Self {
+        assert!(width > 0 && height > 0, "image texture is empty ({width}x{height})");
+        assert_eq!(image.len(), (width * height) as usize * 3, "image size doesn't match {width}x{height}");
+
+        let mut texture = ImageTexture {
+            image,
+            alpha,
+            width,
+            height,
+            filter: Filter::Nearest,
+            wrap_u: Wrap::Clamp,
+            wrap_v: Wrap::Clamp,
+            levels: vec![],
+        };
+        loop {
+            let last = texture.level(texture.levels.len());
+            if last.width == 1 && last.height == 1 {
+                break;
+            }
+            let next = last.downsample();
+            texture.levels.push(next);
+        }
+        texture
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    // Linear RGB texels row by row from the top
+    pub fn image(&self) -> &[f32] {
+        &self.image
+    }
+
+    // Empty when the source image has no alpha channel
+    pub fn alpha_channel(&self) -> &[f32] {
+        &self.alpha
+    }
+
+    // Mip level i, 0 being the full size image
+    fn level(&self, i: usize) -> MipView<'_> {
+        match i {
+            0 => MipView { image: &self.image, alpha: &self.alpha, width: self.width, height: self.height },
+            _ => self.levels[i - 1].view(),
+        }
+    }
+
+    // Trilinear lookup, picking the pair of mip levels whose texels are
+    // closest to the size of the lookup's footprint
+    fn lookup(&self, coord: &TexCoord, texel: impl Fn(MipView, usize) -> Color) -> Color {
+        let footprint = 2.0 * coord.duvdx.0.abs()
+            .max(coord.duvdx.1.abs())
+            .max(coord.duvdy.0.abs())
+            .max(coord.duvdy.1.abs());
+        let size = self.width.max(self.height) as f64;
+        let last = self.levels.len() as f64;
+        let level = (footprint * size).max(1e-8).log2().clamp(0.0, last);
+
+        let sample = |i: usize| {
+            let mip = self.level(i);
+            self.sample(mip, coord.u, coord.v, |j| texel(mip, j))
+        };
+        let fine = level.floor();
+        let t = level - fine;
+        if t == 0.0 {
+            sample(fine as usize)
+        } else {
+            sample(fine as usize) * (1.0 - t) + sample(fine as usize + 1) * t
+        }
+    }
+
+    // Filtered lookup within a single level, `texel` reads the pixel at
+    // a given index
+    fn sample(&self, mip: MipView, u: f64, v: f64, texel: impl Fn(usize) -> Color) -> Color {
+        let (width, height) = (mip.width as i64, mip.height as i64);
+        let fetch = |x: i64, y: i64| {
+            let x = self.wrap_u.apply(x, width);
+            let y = self.wrap_v.apply(y, height);
+            texel((y * width + x) as usize)
+        };
+
+        // Continuous texel coordinates, image rows run top to bottom
+        let x = u * width as f64;
+        let y = (1.0 - v) * height as f64;
+
+        match self.filter {
+            Filter::Nearest => fetch(x.floor() as i64, y.floor() as i64),
+            Filter::Bilinear => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+
+                let top = fetch(x0, y0) * (1.0 - fx) + fetch(x0 + 1, y0) * fx;
+                let bottom = fetch(x0, y0 + 1) * (1.0 - fx) + fetch(x0 + 1, y0 + 1) * fx;
+                top * (1.0 - fy) + bottom * fy
+            },
+            Filter::Bicubic => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let wx = catmull_rom(x - x0);
+                let wy = catmull_rom(y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+
+                let mut c = Color::zero();
+                for (j, wy) in wy.iter().enumerate() {
+                    for (i, wx) in wx.iter().enumerate() {
+                        c += fetch(x0 + i as i64 - 1, y0 + j as i64 - 1) * (wx * wy);
+                    }
+                }
+                // The negative lobes can overshoot below zero
+                Color::new(c.x.max(0.0), c.y.max(0.0), c.z.max(0.0))
+            },
+        }
     }
 }
 
+// Catmull-Rom weights of the four texels around fractional offset t
+fn catmull_rom(t: f64) -> [f64; 4] {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    [
+        0.5 * (-t3 + 2.0*t2 - t),
+        0.5 * (3.0*t3 - 5.0*t2 + 2.0),
+        0.5 * (-3.0*t3 + 4.0*t2 + t),
+        0.5 * (t3 - t2),
+    ]
+}
+
 impl Texture for ImageTexture {
-    fn color(&self, u: f64, v: f64) -> Color {
-        let s = (clamp(u, 0.0, 1.0) * (self.width as f64)) as u32;
-        let t = (clamp(1.0-v, 0.0, 1.0) * (self.height as f64)) as u32;
-        let color_scale = 1.0 / 255.0;
-        Color::new(
-            self.image[((t*self.width*3 + s*3)+0) as usize] as f64 * color_scale,
-            self.image[((t*self.width*3 + s*3)+1) as usize] as f64 * color_scale,
-            self.image[((t*self.width*3 + s*3)+2) as usize] as f64 * color_scale
-        )
+    fn color(&self, coord: &TexCoord) -> Color {
+        self.lookup(coord, |mip, i| Color::new(
+            mip.image[i*3] as f64,
+            mip.image[i*3 + 1] as f64,
+            mip.image[i*3 + 2] as f64
+        ))
+    }
+
+    fn alpha(&self, coord: &TexCoord) -> f64 {
+        if self.alpha.is_empty() {
+            return 1.0;
+        }
+        let a = self.lookup(coord, |mip, i| Color::one() * mip.alpha[i] as f64);
+        a.x.min(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2x1 texture, black on the left and white on the right
+    fn gradient(filter: Filter, wrap: Wrap) -> ImageTexture {
+        let mut texture = ImageTexture::new(vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0], vec![], 2, 1);
+        texture.filter = filter;
+        texture.wrap_u = wrap;
+        texture.wrap_v = wrap;
+        texture
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(Wrap::Repeat.apply(-1, 4), 3);
+        assert_eq!(Wrap::Repeat.apply(5, 4), 1);
+        assert_eq!(Wrap::Mirror.apply(-1, 4), 0);
+        assert_eq!(Wrap::Mirror.apply(4, 4), 3);
+        assert_eq!(Wrap::Mirror.apply(8, 4), 0);
+        assert_eq!(Wrap::Clamp.apply(-3, 4), 0);
+        assert_eq!(Wrap::Clamp.apply(7, 4), 3);
+    }
+
+    #[test]
+    fn test_nearest() {
+        let texture = gradient(Filter::Nearest, Wrap::Repeat);
+        assert_eq!(texture.color(&TexCoord::uv(0.25, 0.5)).x, 0.0);
+        assert_eq!(texture.color(&TexCoord::uv(0.75, 0.5)).x, 1.0);
+        assert_eq!(texture.color(&TexCoord::uv(1.25, 0.5)).x, 0.0);
+        // The far edge no longer reads past the end of the image
+        let texture = gradient(Filter::Nearest, Wrap::Clamp);
+        assert_eq!(texture.color(&TexCoord::uv(1.0, 0.0)).x, 1.0);
+    }
+
+    #[test]
+    fn test_bilinear() {
+        let texture = gradient(Filter::Bilinear, Wrap::Clamp);
+        assert!((texture.color(&TexCoord::uv(0.5, 0.5)).x - 0.5).abs() < 1e-12);
+        assert!(texture.color(&TexCoord::uv(0.25, 0.5)).x.abs() < 1e-12);
+        // Repeating blends the last column back into the first
+        let texture = gradient(Filter::Bilinear, Wrap::Repeat);
+        assert!((texture.color(&TexCoord::uv(0.0, 0.5)).x - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_wrap_per_axis() {
+        // 1x2 texture, white on top and black below
+        let mut texture = ImageTexture::new(vec![1.0, 1.0, 1.0, 0.0, 0.0, 0.0], vec![], 1, 2);
+        texture.filter = Filter::Bilinear;
+        texture.wrap_u = Wrap::Repeat;
+        // Repeating v blends the top row into the bottom one, clamping
+        // keeps the edge rows apart
+        assert!((texture.color(&TexCoord::uv(0.5, 1.0)).x - 1.0).abs() < 1e-12);
+        texture.wrap_v = Wrap::Repeat;
+        assert!((texture.color(&TexCoord::uv(0.5, 1.0)).x - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_bicubic() {
+        let texture = gradient(Filter::Bicubic, Wrap::Clamp);
+        // Interpolates exactly at texel centres
+        assert!(texture.color(&TexCoord::uv(0.25, 0.5)).x.abs() < 1e-12);
+        assert!((texture.color(&TexCoord::uv(0.75, 0.5)).x - 1.0).abs() < 1e-12);
+        assert!((texture.color(&TexCoord::uv(0.5, 0.5)).x - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty() {
+        ImageTexture::new(vec![], vec![], 0, 0);
+    }
+
+    #[test]
+    fn test_catmull_rom_partition() {
+        for i in 0..=10 {
+            let w = catmull_rom(i as f64 / 10.0);
+            assert!((w.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_mipmaps() {
+        let texture = gradient(Filter::Nearest, Wrap::Clamp);
+        assert_eq!(texture.levels.len(), 1);
+
+        // A footprint covering the whole texture reads the 1x1 average
+        let mut coord = TexCoord::uv(0.25, 0.5);
+        coord.duvdx = (0.5, 0.0);
+        assert!((texture.color(&coord).x - 0.5).abs() < 1e-12);
+
+        // Halfway between the two levels
+        coord.duvdx = (2f64.sqrt() / 4.0, 0.0);
+        assert!((texture.color(&coord).x - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_mipmaps_odd() {
+        // The last column of an odd width isn't dropped
+        let texture = ImageTexture::new(vec![0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 3.0, 3.0, 3.0], vec![], 3, 1);
+        assert_eq!(texture.levels.len(), 1);
+        assert_eq!(texture.levels[0].image, vec![1.0; 3]);
+
+        // Nor the last row and column of a 5x3 image, folded into the
+        // second of two 2x1 blocks along with its alpha
+        let mut image = vec![0.0; 5 * 3 * 3];
+        image[(2 * 5 + 4) * 3..].copy_from_slice(&[9.0, 9.0, 9.0]);
+        let mut alpha = vec![1.0; 5 * 3];
+        alpha[2 * 5 + 4] = 0.1;
+        let texture = ImageTexture::new(image, alpha, 5, 3);
+        let level = &texture.levels[0];
+        assert_eq!((level.width, level.height), (2, 1));
+        assert_eq!(level.image[..3], [0.0; 3]);
+        assert_eq!(level.image[3..], [1.0; 3]);
+        assert!((level.alpha[1] - 0.9).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_load_alpha() {
+        // 2x1 PNG, opaque red on the left and fully transparent green on
+        // the right
+        let path = std::env::temp_dir().join("rtir_test_load_alpha.png");
+        image::RgbaImage::from_raw(2, 1, vec![255, 0, 0, 255, 0, 255, 0, 0]).unwrap()
+            .save(&path).unwrap();
+        let texture = ImageTexture::load(path.to_string_lossy().into_owned());
+        assert_eq!(texture.alpha(&TexCoord::uv(0.25, 0.5)), 1.0);
+        assert_eq!(texture.alpha(&TexCoord::uv(0.75, 0.5)), 0.0);
+        // Color is kept under transparent texels
+        assert_eq!(texture.color(&TexCoord::uv(0.75, 0.5)).y, 1.0);
+
+        // Images without an alpha channel are opaque
+        let path = std::env::temp_dir().join("rtir_test_load_no_alpha.png");
+        image::RgbImage::from_raw(1, 1, vec![0, 0, 0]).unwrap().save(&path).unwrap();
+        let texture = ImageTexture::load(path.to_string_lossy().into_owned());
+        assert_eq!(texture.alpha(&TexCoord::uv(0.5, 0.5)), 1.0);
     }
 }
@@ -0,0 +1,25 @@
+use crate::texture::{Texture, TexCoord, Space};
+use crate::texture::perlin::Perlin;
+use crate::vector::Color;
+
+// Marble veins from sine stripes distorted by turbulence
+#[derive(Clone)]
+pub struct Marble {
+    pub perlin: Perlin,
+    pub frequency: f64,
+    pub octaves: u32,
+    pub space: Space,
+    // How strongly turbulence bends the veins
+    pub distortion: f64,
+    pub base_color: Color,
+    pub vein_color: Color,
+}
+
+impl Texture for Marble {
+    fn color(&self, coord: &TexCoord) -> Color {
+        let p = coord.position(self.space) * self.frequency;
+        let stripes = (p.x + self.distortion * self.perlin.turbulence(p, self.octaves)).sin();
+        let t = 0.5 * (1.0 + stripes);
+        self.vein_color + (self.base_color - self.vein_color) * t
+    }
+}
@@ -0,0 +1,37 @@
+use crate::texture::{Texture, TexCoord, Space};
+use crate::texture::perlin::Perlin;
+use crate::vector::Color;
+
+// Fractal Perlin noise as a gray value in [0, 1]
+#[derive(Clone)]
+pub struct Noise {
+    pub perlin: Perlin,
+    pub frequency: f64,
+    pub octaves: u32,
+    pub space: Space,
+}
+
+impl Texture for Noise {
+    fn color(&self, coord: &TexCoord) -> Color {
+        let p = coord.position(self.space) * self.frequency;
+        let n = (0.5 * (1.0 + self.perlin.fbm(p, self.octaves))).clamp(0.0, 1.0);
+        Color::new(n, n, n)
+    }
+}
+
+// Turbulence (sum of absolute noise octaves) as a gray value in [0, 1]
+#[derive(Clone)]
+pub struct Turbulence {
+    pub perlin: Perlin,
+    pub frequency: f64,
+    pub octaves: u32,
+    pub space: Space,
+}
+
+impl Texture for Turbulence {
+    fn color(&self, coord: &TexCoord) -> Color {
+        let p = coord.position(self.space) * self.frequency;
+        let n = self.perlin.turbulence(p, self.octaves).clamp(0.0, 1.0);
+        Color::new(n, n, n)
+    }
+}
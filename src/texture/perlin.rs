@@ -0,0 +1,137 @@
+use crate::vector::Vec3;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+const POINT_COUNT: usize = 256;
+
+// Gradient noise generator (Perlin noise with random unit gradients).
+// The same seed always gives the same noise.
+#[derive(Clone)]
+pub struct Perlin {
+    ranvec: Vec<Vec3<f64>>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let ranvec = (0..POINT_COUNT).map(|_| {
+            loop {
+                let p = Vec3::new(
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0)
+                );
+                let len = p.length_squared();
+                if len > 1e-6 && len < 1.0 {
+                    return p.normalized();
+                }
+            }
+        }).collect();
+
+        let mut permutation = || {
+            let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+            p.shuffle(&mut rng);
+            p
+        };
+        let perm_x = permutation();
+        let perm_y = permutation();
+        let perm_z = permutation();
+
+        Self { ranvec, perm_x, perm_y, perm_z }
+    }
+
+    // Smooth noise in roughly [-1, 1], varying on a scale of one unit
+    pub fn noise(&self, p: Vec3<f64>) -> f64 {
+        let (i, j, k) = (p.x.floor(), p.y.floor(), p.z.floor());
+        let (u, v, w) = (p.x - i, p.y - j, p.z - k);
+        let (i, j, k) = (i as i64, j as i64, k as i64);
+
+        // Hermite smoothing of the interpolation weights
+        let uu = u*u*(3.0 - 2.0*u);
+        let vv = v*v*(3.0 - 2.0*v);
+        let ww = w*w*(3.0 - 2.0*w);
+
+        let mut accum = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let gradient = self.ranvec[
+                        self.perm_x[((i + di) & 255) as usize]
+                        ^ self.perm_y[((j + dj) & 255) as usize]
+                        ^ self.perm_z[((k + dk) & 255) as usize]
+                    ];
+                    let (fi, fj, fk) = (di as f64, dj as f64, dk as f64);
+                    let weight = Vec3::new(u - fi, v - fj, w - fk);
+                    accum += (fi*uu + (1.0 - fi)*(1.0 - uu))
+                        * (fj*vv + (1.0 - fj)*(1.0 - vv))
+                        * (fk*ww + (1.0 - fk)*(1.0 - ww))
+                        * gradient.dot(weight);
+                }
+            }
+        }
+
+        accum
+    }
+
+    // Fractal sum of noise, each octave at twice the frequency and half
+    // the amplitude of the last
+    pub fn fbm(&self, p: Vec3<f64>, octaves: u32) -> f64 {
+        let mut accum = 0.0;
+        let mut temp_p = p;
+        let mut weight = 1.0;
+        for _ in 0..octaves {
+            accum += weight * self.noise(temp_p);
+            weight *= 0.5;
+            temp_p *= 2.0;
+        }
+        accum
+    }
+
+    // Like fbm but summing absolute values, giving the creased look
+    // of turbulence
+    pub fn turbulence(&self, p: Vec3<f64>, octaves: u32) -> f64 {
+        let mut accum = 0.0;
+        let mut temp_p = p;
+        let mut weight = 1.0;
+        for _ in 0..octaves {
+            accum += weight * self.noise(temp_p).abs();
+            weight *= 0.5;
+            temp_p *= 2.0;
+        }
+        accum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deterministic() {
+        let a = Perlin::new(7);
+        let b = Perlin::new(7);
+        let p = Vec3::new(1.3, -2.7, 0.4);
+        assert_eq!(a.noise(p), b.noise(p));
+    }
+
+    #[test]
+    fn test_lattice_zero() {
+        // Gradient noise vanishes at integer lattice points
+        let perlin = Perlin::new(1);
+        assert_eq!(perlin.noise(Vec3::new(3.0, -4.0, 5.0)), 0.0);
+    }
+
+    #[test]
+    fn test_range() {
+        let perlin = Perlin::new(3);
+        for i in 0..1000 {
+            let t = i as f64 * 0.173;
+            let n = perlin.noise(Vec3::new(t, t * 0.7, t * 1.3));
+            assert!((-1.0..=1.0).contains(&n));
+        }
+    }
+}
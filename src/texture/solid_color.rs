@@ -1,4 +1,4 @@
-use crate::texture::Texture;
+use crate::texture::{Texture, TexCoord};
 use crate::vector::Color;
 
 #[derive(Clone)]
@@ -7,7 +7,25 @@ pub struct SolidColor {
 }
 
 impl Texture for SolidColor {
-    fn color(&self, _u: f64, _v: f64) -> Color {
+    fn color(&self, _coord: &TexCoord) -> Color {
         self.color
     }
 }
+
+// Plain values act as constant textures so untextured parameters
+// can be passed directly
+impl Texture for Color {
+    fn color(&self, _coord: &TexCoord) -> Color {
+        *self
+    }
+}
+
+impl Texture for f64 {
+    fn color(&self, _coord: &TexCoord) -> Color {
+        Color::new(*self, *self, *self)
+    }
+
+    fn value(&self, _coord: &TexCoord) -> f64 {
+        *self
+    }
+}
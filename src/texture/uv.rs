@@ -1,11 +1,11 @@
-use crate::texture::Texture;
+use crate::texture::{Texture, TexCoord};
 use crate::vector::Color;
 
 #[derive(Clone)]
 pub struct UVTexture;
 
 impl Texture for UVTexture {
-    fn color(&self, u: f64, v: f64) -> Color {
-        Color::new(u, v, 0.0)
+    fn color(&self, coord: &TexCoord) -> Color {
+        Color::new(coord.u, coord.v, 0.0)
     }
 }
@@ -0,0 +1,29 @@
+use crate::texture::{Texture, TexCoord, Space};
+use crate::texture::perlin::Perlin;
+use crate::vector::Color;
+
+// Concentric growth rings around the z axis, wobbled by noise
+#[derive(Clone)]
+pub struct Wood {
+    pub perlin: Perlin,
+    // Rings per unit distance from the axis
+    pub frequency: f64,
+    pub octaves: u32,
+    pub space: Space,
+    // How strongly noise wobbles the rings
+    pub distortion: f64,
+    pub light_color: Color,
+    pub dark_color: Color,
+}
+
+impl Texture for Wood {
+    fn color(&self, coord: &TexCoord) -> Color {
+        let p = coord.position(self.space);
+        let radius = (p.x*p.x + p.y*p.y).sqrt();
+        let rings = radius * self.frequency
+            + self.distortion * self.perlin.fbm(p * self.frequency, self.octaves);
+        // Sharpen the rings so the dark latewood stays thin
+        let t = (rings - rings.floor()).powi(4);
+        self.light_color + (self.dark_color - self.light_color) * t
+    }
+}
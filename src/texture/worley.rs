@@ -0,0 +1,30 @@
+use crate::texture::{Texture, TexCoord, Space};
+use crate::texture::cellular::Cellular;
+use crate::vector::Color;
+
+// Worley (cellular / Voronoi) noise, the distance to the nearest of a
+// set of randomly scattered feature points as a gray value in [0, 1]
+#[derive(Clone)]
+pub struct Worley {
+    pub cellular: Cellular,
+    pub frequency: f64,
+    pub octaves: u32,
+    pub space: Space,
+}
+
+impl Texture for Worley {
+    fn color(&self, coord: &TexCoord) -> Color {
+        let mut p = coord.position(self.space) * self.frequency;
+        let mut accum = 0.0;
+        let mut weight = 1.0;
+        let mut total = 0.0;
+        for _ in 0..self.octaves.max(1) {
+            accum += weight * self.cellular.distance(p);
+            total += weight;
+            weight *= 0.5;
+            p *= 2.0;
+        }
+        let n = (accum / total).clamp(0.0, 1.0);
+        Color::new(n, n, n)
+    }
+}
@@ -140,16 +140,16 @@ impl Vec3<f64> {
         let cos_theta = (-self).dot(n).min(1.0);
         let r_out_perp = (self + n*cos_theta) * etai_over_etat;
         let r_out_parallel = n * -((1.0 - r_out_perp.length_squared()).abs().sqrt());
-        return r_out_perp + r_out_parallel;
+        r_out_perp + r_out_parallel
     }
 
     pub fn random_in_unit_disk() -> Self {
+        let mut rng = thread_rng();
         loop {
-            let mut p = Vec3::<f64>::zero();
-            let mut rng = thread_rng();
-            p.x += rng.gen_range(-1.0..1.0);
-            p.y += rng.gen_range(-1.0..1.0);
-            return p;
+            let p = Vec3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), 0.0);
+            if p.length_squared() < 1.0 {
+                return p;
+            }
         }
     }
 }